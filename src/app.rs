@@ -1,42 +1,241 @@
 //! UI Code for TMIX
+mod details;
+mod latency;
+mod palette;
+mod patchbay;
+mod prompt;
+mod tab;
 mod ui;
+mod wizard;
 
 use std::{
-    io::{self, Result},
-    thread,
-    time::{Duration, SystemTime},
+    collections::{HashMap, HashSet},
+    io::{self, Error as IOError, Result},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
 };
 
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
 use tui::{
     backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::Style,
     widgets::{Block, Borders},
     Terminal,
 };
 
-use self::ui::ui;
-use tmix::pulse_api::{PulseAPI, VolumeInfo};
+use self::{
+    details::{Details, Outcome as DetailsOutcome},
+    latency::{LatencyHistory, LatencyRow},
+    palette::{Command, Entry, Outcome, Palette},
+    patchbay::{Graph, Node, Routing},
+    prompt::{Outcome as PromptOutcome, Prompt},
+    tab::{
+        adjacent_profile, input_meters, output_meters, playback_meters, recording_meters, Meter,
+        MeterKind, Tab,
+    },
+    ui::{
+        applications_ui, cards_ui, confirm_ui, details_ui, help_ui, latency_ui, meters_ui,
+        modules_ui, palette_ui, patchbay_ui, prompt_ui, scenes_ui, status_ui, tabs_ui, wizard_ui,
+    },
+    wizard::{Choice, Outcome as WizardOutcome, Wizard},
+};
+use tmix::{
+    config::{Action, Config, MeterLayout, Theme, ThemeName},
+    control::{self, MuteAction, VolumeChange},
+    daemon::{Client, Request},
+    data::{limit_percent, CardInformation, ModuleInformation},
+    pulse_api::{Application, Job, Model, Pending, PulseAPI, RecordingInfo, VolumeInfo, Worker},
+    scene::Scene,
+    target::{KillTarget, Selector, Target, TargetKind},
+    virtual_device::{self, VirtualDevice},
+};
+
+/// How far the volume keys move a port's latency offset, in microseconds
+const LATENCY_STEP_USEC: i64 = 10_000;
+
+/// How long a status message stays on screen
+const STATUS_DURATION: Duration = Duration::from_secs(5);
+
+/// Data needed to draw the current tab
+enum ViewData {
+    Meters(Vec<Meter>),
+    Cards(Vec<CardInformation>),
+    Latency(Vec<LatencyRow>),
+    Modules(Vec<ModuleInformation>),
+    Patchbay(Graph),
+    Applications(Vec<Application>),
+    Scenes(Vec<String>),
+}
+
+/// What the server reported for a tab, before it's laid out for drawing
+enum Snapshot {
+    /// The sinks, and the generation of the model they were taken from
+    Sinks(Arc<VolumeInfo>, u64),
+    /// The sinks, when the model is still at the generation on screen
+    Unchanged,
+    Sources(RecordingInfo),
+    Cards(Vec<CardInformation>),
+    Latency(Arc<VolumeInfo>, Vec<CardInformation>),
+    Modules(Vec<ModuleInformation>),
+    Patchbay(Arc<VolumeInfo>, RecordingInfo),
+    Applications(Vec<Application>),
+    Scenes(Vec<String>),
+}
 
-const APP_NAME: &str = "TMIX";
+impl Snapshot {
+    /// Ask the server for what a tab shows, on the worker. Sinks come from the model, which
+    /// is loaded the first time they are asked for. Once loaded it's updated whatever the tab
+    /// shows, so the changes the server reports don't pile up while no tab shows sinks.
+    fn fetch(
+        api: &mut PulseAPI,
+        tab: Tab,
+        model: &Mutex<Option<Model>>,
+        shown: Option<u64>,
+    ) -> Result<Self> {
+        let mut model = model.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(model) = &mut *model {
+            for diff in model.update(api)? {
+                log::trace!("{diff:?}");
+            }
+        }
+        let model = &mut *model;
+        Ok(match tab {
+            Tab::Playback | Tab::OutputDevices => {
+                let model = Self::loaded(api, model)?;
+                match shown == Some(model.generation()) {
+                    true => Snapshot::Unchanged,
+                    false => Snapshot::Sinks(model.volume_info(), model.generation()),
+                }
+            }
+            Tab::Recording | Tab::InputDevices => Snapshot::Sources(api.get_recording_info()?),
+            Tab::Configuration => Snapshot::Cards(api.get_cards()?),
+            Tab::Latency => {
+                let info = Self::loaded(api, model)?.volume_info();
+                Snapshot::Latency(info, api.get_cards()?)
+            }
+            Tab::Modules => Snapshot::Modules(api.get_modules()?),
+            Tab::Patchbay => {
+                let info = Self::loaded(api, model)?.volume_info();
+                Snapshot::Patchbay(info, api.get_recording_info()?)
+            }
+            Tab::Applications => Snapshot::Applications(api.get_applications()?),
+            Tab::Scenes => Snapshot::Scenes(Scene::names().unwrap_or_else(|e| {
+                log::warn!("Failed to list scenes: {e}");
+                vec![]
+            })),
+        })
+    }
+
+    /// The model, loading it the first time it's needed
+    fn loaded<'a>(api: &mut PulseAPI, model: &'a mut Option<Model>) -> Result<&'a mut Model> {
+        let loaded = match model.take() {
+            Some(loaded) => loaded,
+            None => Model::load(api)?,
+        };
+        Ok(model.insert(loaded))
+    }
+}
+
+/// A popup drawn over the current view, which gets every key press while open
+enum Overlay {
+    Help,
+    Palette(Palette),
+    Details(Details),
+    /// Asks before making a change that can't be undone
+    Confirm {
+        prompt: String,
+        change: Change,
+    },
+    /// Asks for a module to load, and its arguments
+    LoadModule(Prompt),
+    /// Guides through creating a virtual device
+    Wizard(Wizard),
+}
+
+/// A change that is only made once confirmed
+enum Change {
+    Kill(Vec<KillTarget>),
+    UnloadModule(u32),
+}
 
 /// Application Manager For TMIX
 pub struct App {
     terminal: Option<Terminal<CrosstermBackend<io::Stdout>>>,
-    api: PulseAPI,
+    /// The connection to the server, which runs every request on a thread of its own
+    api: Worker,
+    /// The data being fetched, and the tab it is for
+    fetching: Option<(Tab, Pending<Snapshot>)>,
+    /// An overlay waiting on the server for what it shows
+    opening: Option<Pending<Option<Overlay>>>,
+    /// The sinks and sink inputs, kept up to date by the worker
+    model: Arc<Mutex<Option<Model>>>,
+    /// The generation of the model on screen, when the current tab shows sinks
+    shown: Option<u64>,
+    /// Whether anything changed since the screen was last drawn
+    redraw: bool,
+    /// Changes the worker hasn't reported on yet, with what they do
+    changes: Vec<(String, Pending<()>)>,
+    /// The last thing that went wrong, and when, shown at the bottom of the screen for a while
+    status: Option<(String, Instant)>,
+    config: Config,
+    /// The built-in theme being shown, cycled at runtime
+    theme_name: ThemeName,
+    theme: Theme,
+    /// How meters are drawn, toggled at runtime
+    layout: MeterLayout,
+    tab: Tab,
+    /// Index of the selected item in the current tab
+    selected: usize,
+    /// The selection of every other tab, restored when switching back to it
+    focus: HashMap<Tab, usize>,
+    /// Sinks whose streams are hidden in the output devices tree
+    collapsed: HashSet<u32>,
+    latency: LatencyHistory,
+    /// The stream picked up in the patchbay, to be moved onto the next device picked
+    routing: Option<Routing>,
+    overlay: Option<Overlay>,
+    /// Only show devices, streams and applications containing this text
+    filter: Option<String>,
+    /// Whether keys are being typed into the filter
+    searching: bool,
+    running: bool,
 }
 
 impl App {
-    pub fn try_new() -> Result<Self> {
-        let mut api = PulseAPI::new();
-        api.startup_connection()?;
+    pub fn try_new(config: Config) -> Result<Self> {
+        let api = Worker::connect(config.server.address.as_deref(), config.server.timeouts())?;
         Ok(Self {
             terminal: None,
             api,
+            fetching: None,
+            opening: None,
+            model: Arc::new(Mutex::new(None)),
+            shown: None,
+            redraw: true,
+            changes: vec![],
+            status: None,
+            tab: config.general.default_view.into(),
+            theme_name: config.theme.name,
+            theme: config.theme.theme(config.theme.name),
+            layout: config.general.layout,
+            config,
+            selected: 0,
+            focus: HashMap::new(),
+            collapsed: HashSet::new(),
+            latency: LatencyHistory::default(),
+            routing: None,
+            overlay: None,
+            filter: None,
+            searching: false,
+            running: true,
         })
     }
 
@@ -44,17 +243,848 @@ impl App {
     pub fn run(&mut self) -> Result<()> {
         // Setup a Main Loop
         self.start_up_tui()?;
-        // Do Some Thing!
-        let now = SystemTime::now();
-        while now.elapsed().expect("Something broke in time") < Duration::from_millis(15000) {
-            let data = self.api.get_volume_info()?;
-            self.draw_data(data)?;
-            thread::sleep(Duration::from_millis(10));
+        // Nothing is shown until the server first answers
+        let mut data = ViewData::Meters(vec![]);
+        while self.running {
+            if let Some(fresh) = self.refresh() {
+                data = fresh;
+                self.redraw = true;
+            }
+            self.open_ready_overlay();
+            self.check_changes();
+            if self
+                .status
+                .as_ref()
+                .is_some_and(|(_, at)| at.elapsed() > STATUS_DURATION)
+            {
+                self.status = None;
+                self.redraw = true;
+            }
+            // Nothing on screen changes unless new data came in or something happened
+            if self.redraw {
+                // Items may have gone away since the selection was made, or remembered for the
+                // tab
+                self.selected = self.selected.min(self.item_count(&data).saturating_sub(1));
+                self.draw_data(&data)?;
+                self.redraw = false;
+            }
+            if event::poll(self.config.general.poll_interval)? {
+                // Resizes and the like need a redraw too
+                self.redraw = true;
+                if let Event::Key(key) = event::read()? {
+                    self.handle_key(key, &data);
+                }
+            }
         }
         self.shut_down_api();
         self.shut_down_tui()
     }
 
+    /// Ask the worker for what the current tab shows
+    fn fetch(&mut self) {
+        let (tab, shown) = (self.tab, self.shown);
+        let model = self.model.clone();
+        let pending = self
+            .api
+            .request(move |api| Snapshot::fetch(api, tab, &model, shown));
+        self.fetching = Some((tab, pending));
+    }
+
+    /// Take the tab's data once the worker has fetched it and ask for it again, so the server
+    /// is never waited on here. Data fetched for a tab that was since left is dropped. When the
+    /// server fails to answer, the last data stays on screen and it's asked again.
+    fn refresh(&mut self) -> Option<ViewData> {
+        let Some((tab, pending)) = self.fetching.take() else {
+            self.fetch();
+            return None;
+        };
+        let Some(snapshot) = pending.try_take() else {
+            self.fetching = Some((tab, pending));
+            return None;
+        };
+        self.fetch();
+        match snapshot {
+            Ok(Snapshot::Unchanged) => None,
+            Ok(snapshot) if tab == self.tab => {
+                self.shown = match snapshot {
+                    Snapshot::Sinks(_, generation) => Some(generation),
+                    _ => None,
+                };
+                Some(self.view(tab, snapshot))
+            }
+            Ok(_) => None,
+            Err(e) => {
+                self.report(format!("Failed to update the {}: {e}", tab.title()));
+                None
+            }
+        }
+    }
+
+    /// Show what went wrong at the bottom of the screen, as the log can't be seen while the
+    /// mixer is open
+    fn report(&mut self, message: String) {
+        log::warn!("{message}");
+        self.status = Some((message, Instant::now()));
+        self.redraw = true;
+    }
+
+    /// Lay out what the server reported for a tab
+    fn view(&mut self, tab: Tab, snapshot: Snapshot) -> ViewData {
+        let filter = self.filter.as_deref();
+        match (tab, snapshot) {
+            (Tab::OutputDevices, Snapshot::Sinks(info, _)) => {
+                ViewData::Meters(output_meters(&info, filter, &self.collapsed))
+            }
+            (_, Snapshot::Sinks(info, _)) => ViewData::Meters(playback_meters(&info, filter)),
+            (_, Snapshot::Unchanged) => unreachable!("unchanged sinks are never laid out"),
+            (Tab::InputDevices, Snapshot::Sources(info)) => {
+                ViewData::Meters(input_meters(&info, filter))
+            }
+            (_, Snapshot::Sources(info)) => ViewData::Meters(recording_meters(&info, filter)),
+            (_, Snapshot::Cards(mut cards)) => {
+                if let Some(filter) = filter {
+                    cards.retain(|card| card.matches_text(filter));
+                }
+                ViewData::Cards(cards)
+            }
+            (_, Snapshot::Latency(info, cards)) => {
+                let mut rows = self.latency.rows(&info, &cards);
+                if let Some(filter) = filter {
+                    let filter = filter.to_lowercase();
+                    rows.retain(|row| row.title.to_lowercase().contains(&filter));
+                }
+                ViewData::Latency(rows)
+            }
+            (_, Snapshot::Modules(mut modules)) => {
+                if let Some(filter) = filter {
+                    modules.retain(|module| module.matches_text(filter));
+                }
+                ViewData::Modules(modules)
+            }
+            (_, Snapshot::Patchbay(info, recording)) => {
+                ViewData::Patchbay(Graph::new(&info, &recording, filter))
+            }
+            (_, Snapshot::Applications(mut apps)) => {
+                if let Some(filter) = filter {
+                    apps.retain(|app| app.matches_text(filter));
+                }
+                ViewData::Applications(apps)
+            }
+            (_, Snapshot::Scenes(names)) => ViewData::Scenes(names),
+        }
+    }
+
+    /// Queue a change, reported on once the worker has made it. Data already being fetched
+    /// may predate the change, so it's fetched again.
+    fn change(&mut self, what: String, job: Job<()>) {
+        self.changes.push((what, self.api.request(job)));
+        self.fetching = None;
+    }
+
+    /// Report the changes the server refused
+    fn check_changes(&mut self) {
+        let mut failed = vec![];
+        self.changes
+            .retain(|(what, pending)| match pending.try_take() {
+                Some(Ok(())) => false,
+                Some(Err(e)) => {
+                    failed.push(format!("Failed to {what}: {e}"));
+                    false
+                }
+                None => true,
+            });
+        for message in failed {
+            self.report(message);
+        }
+    }
+
+    /// Open an overlay once the worker has fetched what it shows
+    fn open_when_ready<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PulseAPI) -> Result<Option<Overlay>> + Send + 'static,
+    {
+        self.opening = Some(self.api.request(f));
+    }
+
+    /// Show the overlay that was waiting on the server, unless another one was opened since
+    fn open_ready_overlay(&mut self) {
+        let Some(result) = self.opening.as_ref().and_then(|pending| pending.try_take()) else {
+            return;
+        };
+        self.opening = None;
+        match result {
+            Ok(overlay) if self.overlay.is_none() => {
+                self.overlay = overlay;
+                self.redraw = true;
+            }
+            Ok(_) => {}
+            Err(e) => self.report(format!("Failed to fetch what to show: {e}")),
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, data: &ViewData) {
+        if self.searching {
+            self.handle_search_key(key);
+            return;
+        }
+        match &mut self.overlay {
+            // Any key closes the help
+            Some(Overlay::Help) => self.overlay = None,
+            Some(Overlay::Confirm { .. }) => {
+                let Some(Overlay::Confirm { change, .. }) = self.overlay.take() else {
+                    unreachable!("matched above");
+                };
+                if let KeyCode::Char('y' | 'Y') = key.code {
+                    self.make_change(change);
+                }
+            }
+            Some(Overlay::LoadModule(prompt)) => match prompt.handle_key(key) {
+                PromptOutcome::Continue => {}
+                PromptOutcome::Close => self.overlay = None,
+                PromptOutcome::Submit(line) => {
+                    self.overlay = None;
+                    self.load_module(&line);
+                }
+            },
+            Some(Overlay::Wizard(wizard)) => match wizard.handle_key(key) {
+                WizardOutcome::Continue => {}
+                WizardOutcome::Close => self.overlay = None,
+                // Arguments are checked here, so mistakes can be fixed before the wizard closes
+                WizardOutcome::Create(device) => match device.argument() {
+                    Ok(_) => {
+                        self.overlay = None;
+                        self.create_virtual_device(device);
+                    }
+                    Err(e) => wizard.error = Some(e.to_string()),
+                },
+            },
+            Some(Overlay::Details(details)) => match details.handle_key(key) {
+                DetailsOutcome::Continue => {}
+                DetailsOutcome::Close => self.overlay = None,
+            },
+            Some(Overlay::Palette(palette)) => match palette.handle_key(key) {
+                Outcome::Continue => {}
+                Outcome::Close => self.overlay = None,
+                Outcome::Run(command) => {
+                    self.overlay = None;
+                    self.run_command(command, data);
+                }
+            },
+            // Escape puts down a stream picked up in the patchbay, rather than quitting
+            None if self.routing.is_some() && key.code == KeyCode::Esc => self.routing = None,
+            None => {
+                if let Some(action) = self.config.keybindings.action(key) {
+                    self.perform(action, data);
+                }
+            }
+        }
+    }
+
+    /// Edit the filter as it's typed, so the view narrows down with every key
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        let filter = self.filter.get_or_insert_with(String::new);
+        match key.code {
+            KeyCode::Esc => {
+                self.filter = None;
+                self.searching = false;
+            }
+            KeyCode::Enter => {
+                if filter.is_empty() {
+                    self.filter = None;
+                }
+                self.searching = false;
+            }
+            KeyCode::Backspace => {
+                filter.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                filter.push(c);
+                self.selected = 0;
+            }
+            _ => return,
+        }
+        self.relayout();
+    }
+
+    /// Number of items the current tab can select between
+    fn item_count(&self, data: &ViewData) -> usize {
+        match data {
+            ViewData::Meters(meters) => meters.len(),
+            ViewData::Cards(cards) => cards.len(),
+            ViewData::Latency(rows) => rows.len(),
+            ViewData::Modules(modules) => modules.len(),
+            ViewData::Patchbay(graph) => graph.nodes.len(),
+            ViewData::Applications(apps) => apps.len(),
+            ViewData::Scenes(names) => names.len(),
+        }
+    }
+
+    /// Switch to another tab, remembering what was selected on this one
+    fn switch_to(&mut self, tab: Tab) {
+        if tab == self.tab {
+            return;
+        }
+        self.focus.insert(self.tab, self.selected);
+        self.selected = self.focus.get(&tab).copied().unwrap_or(0);
+        self.tab = tab;
+        self.routing = None;
+        // What is being fetched is for the tab that was left
+        self.relayout();
+    }
+
+    /// Lay the current tab out again from what is fetched next, after a change to what it
+    /// shows. Sinks that haven't changed are otherwise left as they were laid out.
+    fn relayout(&mut self) {
+        self.shown = None;
+        self.fetching = None;
+    }
+
+    fn perform(&mut self, action: Action, data: &ViewData) {
+        if let Some(tab) = Tab::for_action(action) {
+            return self.switch_to(tab);
+        }
+        match action {
+            Action::Quit => self.running = false,
+            Action::NextView => return self.switch_to(self.tab.next()),
+            Action::PreviousView => return self.switch_to(self.tab.previous()),
+            Action::SelectPrevious => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            Action::SelectNext => {
+                self.selected = self.selected.saturating_add(1);
+            }
+            Action::CycleTheme => {
+                self.theme_name = self.theme_name.next();
+                self.theme = self.config.theme.theme(self.theme_name);
+            }
+            Action::ToggleLayout => self.layout = self.layout.toggle(),
+            Action::Search => {
+                self.searching = true;
+                self.filter = Some(String::new());
+                self.selected = 0;
+                self.relayout();
+            }
+            // Every item shown matches the filter, so cycling matches wraps around the view
+            Action::SearchNext => {
+                let count = self.item_count(data).max(1);
+                self.selected = (self.selected + 1) % count;
+            }
+            Action::SearchPrevious => {
+                let count = self.item_count(data).max(1);
+                self.selected = (self.selected + count - 1) % count;
+            }
+            Action::Help => self.overlay = Some(Overlay::Help),
+            Action::Details => self.details(data),
+            Action::Kill => self.overlay = self.confirm_change(data),
+            Action::LoadModule => {
+                let prompt = Prompt::new("Load a module, e.g. module-null-sink sink_name=virtual");
+                self.overlay = Some(Overlay::LoadModule(prompt));
+            }
+            Action::NewVirtualDevice => self.wizard(),
+            Action::CommandPalette => self.palette(data),
+            _ => {}
+        }
+
+        self.selected = self.selected.min(self.item_count(data).saturating_sub(1));
+
+        let step = self.config.general.volume_step;
+        let job: Option<Job<()>> = match data {
+            ViewData::Meters(meters) => match (meters.get(self.selected), action) {
+                (Some(_), Action::Expand | Action::Collapse | Action::ToggleExpand) => {
+                    self.expand(meters, action);
+                    self.relayout();
+                    None
+                }
+                (Some(meter), _) => self.change_meter(meter, action, step),
+                (None, _) => None,
+            },
+            ViewData::Cards(cards) => match (cards.get(self.selected), action) {
+                (Some(card), Action::VolumeUp) => self.cycle_profile(card, 1),
+                (Some(card), Action::VolumeDown) => self.cycle_profile(card, -1),
+                _ => None,
+            },
+            ViewData::Latency(rows) => match (rows.get(self.selected), action) {
+                (Some(row), Action::VolumeUp) => self.change_latency_offset(row, LATENCY_STEP_USEC),
+                (Some(row), Action::VolumeDown) => {
+                    self.change_latency_offset(row, -LATENCY_STEP_USEC)
+                }
+                _ => None,
+            },
+            ViewData::Modules(_) => None,
+            ViewData::Patchbay(graph) => match (graph.nodes.get(self.selected), action) {
+                (Some(node), Action::Activate) => self.route(node),
+                _ => None,
+            },
+            ViewData::Applications(apps) => match apps.get(self.selected) {
+                Some(app) => match action {
+                    Action::VolumeUp => Some(self.change_volume(app, step)),
+                    Action::VolumeDown => Some(self.change_volume(app, -step)),
+                    Action::ToggleMute => {
+                        let (app, mute) = (app.clone(), !app.mute());
+                        Some(Box::new(move |api| api.set_application_mute(&app, mute)))
+                    }
+                    _ => None,
+                },
+                None => None,
+            },
+            ViewData::Scenes(names) => {
+                if let (Action::Activate, Some(name)) = (action, names.get(self.selected)) {
+                    self.apply_scene(name);
+                }
+                None
+            }
+        };
+        if let Some(job) = job {
+            self.change(format!("update the {}", self.tab.title()), job);
+        }
+    }
+
+    /// Show or hide the streams of the selected sink in the output devices tree. Collapsing from
+    /// one of the streams collapses its sink and selects it.
+    fn expand(&mut self, meters: &[Meter], action: Action) {
+        let meter = &meters[self.selected];
+        let sink = match meter.kind {
+            MeterKind::Sink => meter.index,
+            MeterKind::SinkInput if action != Action::Expand && self.tab == Tab::OutputDevices => {
+                let Some(sink) = meter.parent else {
+                    return;
+                };
+                if let Some(position) = meters
+                    .iter()
+                    .position(|m| m.kind == MeterKind::Sink && m.index == sink)
+                {
+                    self.selected = position;
+                }
+                self.collapsed.insert(sink);
+                return;
+            }
+            _ => return,
+        };
+        let collapse = match action {
+            Action::Expand => false,
+            Action::Collapse => true,
+            _ => !self.collapsed.contains(&sink),
+        };
+        if collapse {
+            self.collapsed.insert(sink);
+        } else {
+            self.collapsed.remove(&sink);
+        }
+    }
+
+    /// The change to the volume or mute of the stream or device behind a meter, or making the
+    /// device the default
+    fn change_meter(&self, meter: &Meter, action: Action, step: f64) -> Option<Job<()>> {
+        let scale = self.config.general.scale;
+        let (kind, index, mute) = (meter.kind, meter.index, !meter.mute);
+        match action {
+            Action::VolumeUp | Action::VolumeDown if meter.volume_writable => {
+                let step = match action {
+                    Action::VolumeUp => step,
+                    _ => -step,
+                };
+                let mut channels = meter.volume;
+                let current = scale.to_percent(channels.avg());
+                let percent =
+                    limit_percent(current + step, current, self.config.general.max_volume);
+                channels.set(channels.len(), scale.to_volume(percent));
+                let job: Job<()> = Box::new(move |api| match kind {
+                    MeterKind::SinkInput => api.set_sink_input_volume(index, &channels),
+                    MeterKind::SourceOutput => api.set_source_output_volume(index, &channels),
+                    MeterKind::Sink => api.set_sink_volume(index, &channels),
+                    MeterKind::Source => api.set_source_volume(index, &channels),
+                });
+                Some(match target(kind, index) {
+                    Some(target) => {
+                        let volume = VolumeChange::Set(percent);
+                        self.via_daemon(Request::Set { target, volume }, job)
+                    }
+                    None => job,
+                })
+            }
+            Action::ToggleMute => {
+                let job: Job<()> = Box::new(move |api| match kind {
+                    MeterKind::SinkInput => api.set_sink_input_mute(index, mute),
+                    MeterKind::SourceOutput => api.set_source_output_mute(index, mute),
+                    MeterKind::Sink => api.set_sink_mute(index, mute),
+                    MeterKind::Source => api.set_source_mute(index, mute),
+                });
+                Some(match target(kind, index) {
+                    Some(target) => {
+                        let action = if mute {
+                            MuteAction::On
+                        } else {
+                            MuteAction::Off
+                        };
+                        self.via_daemon(Request::Mute { target, action }, job)
+                    }
+                    None => job,
+                })
+            }
+            Action::Activate => {
+                let name = meter.name.clone()?;
+                match kind {
+                    MeterKind::Sink => Some(self.set_default_sink(name)),
+                    MeterKind::Source => Some(Box::new(move |api| api.set_default_source(&name))),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Pick up the selected stream in the patchbay, or move the stream picked up onto the
+    /// selected device. Picking up the same stream again puts it back down.
+    fn route(&mut self, node: &Node) -> Option<Job<()>> {
+        match self.routing.take() {
+            Some(routing) if routing.kind.target() == Some(node.kind) => {
+                let (stream, device) = (routing.index, node.index);
+                Some(match routing.kind {
+                    MeterKind::SinkInput => self.move_sink_input(stream, device),
+                    _ => Box::new(move |api| api.move_source_output(stream, device)),
+                })
+            }
+            Some(routing) if routing.kind == node.kind && routing.index == node.index => None,
+            routing => {
+                self.routing = match node.kind.target() {
+                    Some(_) => Some(Routing {
+                        kind: node.kind,
+                        index: node.index,
+                        title: node.title.clone(),
+                    }),
+                    None => routing,
+                };
+                None
+            }
+        }
+    }
+
+    /// Move the latency offset of the port a sink plays through
+    fn change_latency_offset(&self, row: &LatencyRow, step: i64) -> Option<Job<()>> {
+        let port = row.port.as_ref()?;
+        let (card, name, offset) = (port.card.clone(), port.port.clone(), port.offset + step);
+        Some(Box::new(move |api| {
+            api.set_port_latency_offset(&card, &name, offset)
+        }))
+    }
+
+    /// Switch the card to the available profile `step` places away from its active one
+    fn cycle_profile(&self, card: &CardInformation, step: isize) -> Option<Job<()>> {
+        let profile = adjacent_profile(card, step)?.name.clone()?;
+        let index = card.index;
+        Some(Box::new(move |api| api.set_card_profile(index, &profile)))
+    }
+
+    /// Ask whether to disconnect the selected stream or every client of the selected
+    /// application, or to unload the selected module
+    fn confirm_change(&self, data: &ViewData) -> Option<Overlay> {
+        let (prompt, targets) = match data {
+            ViewData::Meters(meters) => {
+                let meter = meters.get(self.selected)?;
+                let selector = Selector::Index(meter.index);
+                let target = match meter.kind {
+                    MeterKind::SinkInput => KillTarget::SinkInput(selector),
+                    MeterKind::SourceOutput => KillTarget::SourceOutput(selector),
+                    MeterKind::Sink | MeterKind::Source => return None,
+                };
+                (
+                    format!(
+                        "Disconnect the stream {}?",
+                        meter.title.trim_start_matches([' ', '├', '└'])
+                    ),
+                    vec![target],
+                )
+            }
+            ViewData::Applications(apps) => {
+                let app = apps.get(self.selected)?;
+                let targets = app
+                    .clients()
+                    .map(|c| KillTarget::Client(Selector::Index(c.index)))
+                    .collect();
+                (
+                    format!("Disconnect {} and all its streams?", app.name()),
+                    targets,
+                )
+            }
+            ViewData::Modules(modules) => {
+                let module = modules.get(self.selected)?;
+                let name = module.name.as_deref().unwrap_or_default();
+                return Some(Overlay::Confirm {
+                    prompt: format!("Unload module {} {name}?", module.index),
+                    change: Change::UnloadModule(module.index),
+                });
+            }
+            _ => return None,
+        };
+        Some(Overlay::Confirm {
+            prompt,
+            change: Change::Kill(targets),
+        })
+    }
+
+    fn make_change(&mut self, change: Change) {
+        match change {
+            Change::Kill(targets) => {
+                for target in targets {
+                    let what = format!("kill {target}");
+                    let request = Request::Kill {
+                        target: target.clone(),
+                    };
+                    let kill = self.via_daemon(
+                        request,
+                        Box::new(move |api| control::kill(api, &target).map_err(IOError::other)),
+                    );
+                    self.change(what, kill);
+                }
+            }
+            Change::UnloadModule(index) => self.change(
+                format!("unload module {index}"),
+                Box::new(move |api| api.unload_module(index)),
+            ),
+        }
+    }
+
+    /// Load a module from a line like `module-loopback latency_msec=20`
+    fn load_module(&mut self, line: &str) {
+        let (name, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if name.is_empty() {
+            return;
+        }
+        let (name, argument) = (name.to_string(), argument.trim().to_string());
+        self.change(
+            format!("load {name}"),
+            Box::new(move |api| {
+                let index = api.load_module(&name, &argument)?;
+                log::info!("Loaded {name} as module {index}");
+                Ok(())
+            }),
+        );
+    }
+
+    fn create_virtual_device(&mut self, device: VirtualDevice) {
+        self.change(
+            format!("create the {}", device.kind()),
+            Box::new(move |api| {
+                let module = virtual_device::create(api, &device).map_err(IOError::other)?;
+                log::info!("Created {} as module {module}", device.kind());
+                Ok(())
+            }),
+        );
+    }
+
+    /// Open the virtual device wizard, able to pick any sink or source
+    fn wizard(&mut self) {
+        self.open_when_ready(|api| {
+            let info = api.get_volume_info()?;
+            let recording = api.get_recording_info()?;
+            let choice = |description: &Option<String>, name: &Option<String>| {
+                let name = name.clone()?;
+                Some(Choice::new(
+                    description.clone().unwrap_or(name.clone()),
+                    name,
+                ))
+            };
+            let sinks = info
+                .iter()
+                .filter_map(|(_, entry)| choice(&entry.sink().description, &entry.sink().name))
+                .collect();
+            let sources = recording
+                .sources()
+                .filter_map(|source| choice(&source.description, &source.name))
+                .collect();
+            Ok(Some(Overlay::Wizard(Wizard::new(sinks, sources))))
+        });
+    }
+
+    /// Show every property of the selected item. Streams and devices are fetched afresh, as
+    /// the meters only keep a summary.
+    fn details(&mut self, data: &ViewData) {
+        let details = match data {
+            ViewData::Meters(meters) => {
+                let Some(meter) = meters.get(self.selected) else {
+                    return;
+                };
+                let (kind, index) = (meter.kind, meter.index);
+                return self.open_when_ready(move |api| {
+                    let details = match kind {
+                        MeterKind::Sink => api.get_volume_info()?.sink(index).map(Details::sink),
+                        MeterKind::SinkInput => api
+                            .get_volume_info()?
+                            .sink_input(index)
+                            .map(Details::sink_input),
+                        MeterKind::Source => {
+                            api.get_recording_info()?.source(index).map(Details::source)
+                        }
+                        MeterKind::SourceOutput => api
+                            .get_recording_info()?
+                            .source_outputs()
+                            .find(|o| o.index == index)
+                            .map(Details::source_output),
+                    };
+                    Ok(details.map(Overlay::Details))
+                });
+            }
+            ViewData::Cards(cards) => cards.get(self.selected).map(Details::card),
+            ViewData::Latency(_) | ViewData::Modules(_) | ViewData::Patchbay(_) => None,
+            ViewData::Applications(apps) => apps.get(self.selected).map(Details::application),
+            ViewData::Scenes(_) => None,
+        };
+        self.overlay = details.map(Overlay::Details);
+    }
+
+    /// Open the command palette, offering everything that can be done from the current state
+    fn palette(&mut self, data: &ViewData) {
+        let mut entries: Vec<Entry> = self
+            .config
+            .keybindings
+            .iter()
+            .map(|(action, _)| action)
+            .filter(|action| *action != Action::CommandPalette)
+            .map(|action| Entry::new(action.description(), Command::Action(action)))
+            .collect();
+
+        // The streams of the selected application or playback stream, which can be moved
+        let selected_streams: Option<(String, Vec<u32>)> = match data {
+            ViewData::Applications(apps) => apps
+                .get(self.selected)
+                .map(|app| (app.name(), app.sink_inputs().map(|i| i.index).collect())),
+            ViewData::Meters(meters) => meters
+                .get(self.selected)
+                .filter(|meter| meter.kind == MeterKind::SinkInput)
+                .map(|meter| (meter.title.clone(), vec![meter.index])),
+            _ => None,
+        };
+        self.open_when_ready(move |api| {
+            let info = api.get_volume_info()?;
+            for (_, entry) in info.iter() {
+                let sink = entry.sink();
+                let label = sink
+                    .description
+                    .clone()
+                    .or_else(|| sink.name.clone())
+                    .unwrap_or_else(|| format!("Sink {}", sink.index));
+                if let Some(name) = &sink.name {
+                    entries.push(Entry::new(
+                        format!("Set default sink: {label}"),
+                        Command::SetDefaultSink(name.clone()),
+                    ));
+                }
+                if let Some((name, inputs)) = &selected_streams {
+                    entries.push(Entry::new(
+                        format!("Move {name} to: {label}"),
+                        Command::MoveStreams {
+                            inputs: inputs.clone(),
+                            sink: sink.index,
+                        },
+                    ));
+                }
+            }
+
+            match Scene::names() {
+                Ok(names) => entries.extend(names.into_iter().map(|name| {
+                    Entry::new(format!("Apply scene: {name}"), Command::ApplyScene(name))
+                })),
+                Err(e) => log::warn!("Failed to list scenes: {e}"),
+            }
+            match virtual_device::list(api) {
+                Ok(devices) => entries.extend(devices.into_iter().map(|device| {
+                    Entry::new(
+                        format!("Remove virtual device: {device}"),
+                        Command::RemoveVirtualDevice(device.module),
+                    )
+                })),
+                Err(e) => log::warn!("Failed to list virtual devices: {e}"),
+            }
+            Ok(Some(Overlay::Palette(Palette::new(entries))))
+        });
+    }
+
+    fn run_command(&mut self, command: Command, data: &ViewData) {
+        match command {
+            Command::Action(action) => self.perform(action, data),
+            Command::MoveStreams { inputs, sink } => {
+                for input in inputs {
+                    let job = self.move_sink_input(input, sink);
+                    self.change(format!("move stream {input}"), job);
+                }
+            }
+            Command::SetDefaultSink(name) => {
+                let job = self.set_default_sink(name.clone());
+                self.change(format!("set the default sink to {name}"), job);
+            }
+            Command::ApplyScene(name) => self.apply_scene(&name),
+            Command::RemoveVirtualDevice(module) => self.change(
+                format!("remove the virtual device of module {module}"),
+                Box::new(move |api| api.unload_module(module)),
+            ),
+        }
+    }
+
+    /// Apply a scene, through the daemon when one is running so it stays the one place scenes
+    /// are applied from
+    fn apply_scene(&mut self, name: &str) {
+        let scene = name.to_string();
+        let job = self.via_daemon(
+            Request::ApplyScene {
+                name: name.to_string(),
+            },
+            Box::new(move |api| {
+                Scene::load(&scene)
+                    .and_then(|scene| scene.apply(api))
+                    .map_err(IOError::other)
+            }),
+        );
+        self.change(format!("apply scene {name}"), job);
+    }
+
+    fn set_default_sink(&self, name: String) -> Job<()> {
+        let request = Request::Default {
+            sink: Selector::Name(name.clone()),
+        };
+        self.via_daemon(request, Box::new(move |api| api.set_default_sink(&name)))
+    }
+
+    fn move_sink_input(&self, input: u32, sink: u32) -> Job<()> {
+        let request = Request::Move {
+            input: Target {
+                kind: TargetKind::SinkInput,
+                selector: Selector::Index(input),
+            },
+            sink: Selector::Index(sink),
+        };
+        self.via_daemon(
+            request,
+            Box::new(move |api| api.move_sink_input(input, sink)),
+        )
+    }
+
+    /// Make a change through the daemon when one is running, as the command line does, and
+    /// with `job` over our own connection otherwise. Either way it's done on the worker, as the
+    /// daemon may take as long as the server to answer.
+    fn via_daemon(&self, request: Request, job: Job<()>) -> Job<()> {
+        let socket = self.config.daemon.socket_path();
+        Box::new(move |api| {
+            let Ok(mut client) = Client::connect(&socket) else {
+                return job(api);
+            };
+            let response = client.request(&request)?;
+            match response.ok {
+                true => Ok(()),
+                false => Err(IOError::other(response.error.unwrap_or_default())),
+            }
+        })
+    }
+
+    /// The change moving the application's master volume by `step` percent
+    fn change_volume(&self, app: &Application, step: f64) -> Job<()> {
+        let scale = self.config.general.scale;
+        let current = scale.to_percent(app.volume());
+        let percent = limit_percent(current + step, current, self.config.general.max_volume);
+        let volume = scale.to_volume(percent);
+        let app = app.clone();
+        Box::new(move |api| api.set_application_volume(&app, volume))
+    }
+
     fn start_up_tui(&mut self) -> Result<()> {
         enable_raw_mode()?;
 
@@ -66,15 +1096,67 @@ impl App {
         Ok(())
     }
 
-    fn draw_data(&mut self, data: VolumeInfo) -> Result<()> {
+    fn draw_data(&mut self, data: &ViewData) -> Result<()> {
+        let mut title = self.config.general.title.clone();
+        match (&self.filter, self.searching) {
+            (Some(filter), true) => title.push_str(&format!(" /{filter}")),
+            (Some(filter), false) => title.push_str(&format!(" [/{filter}]")),
+            (None, _) => {}
+        }
+        let tab = self.tab;
+        let layout = self.layout;
+        let selected = self.selected;
+        let config = &self.config;
+        let theme = &self.theme;
+        let overlay = &self.overlay;
+        let routing = self.routing.as_ref();
+        let status = self.status.as_ref().map(|(message, _)| message.as_str());
         self.terminal
             .as_mut()
             .expect("don't draw till intialized")
             .draw(|f| {
                 let size = f.size();
-                let block = Block::default().title(APP_NAME).borders(Borders::ALL);
+                let block = Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(theme.text).bg(theme.background))
+                    .border_style(Style::default().fg(theme.border));
+                let inner = block.inner(size);
                 f.render_widget(block, size);
-                ui(f, data);
+                let footer = Constraint::Length(status.map_or(0, |_| 1));
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(1), Constraint::Min(0), footer])
+                    .split(inner);
+                let (header, body) = (chunks[0], chunks[1]);
+                tabs_ui(f, header, tab, &config.keybindings, theme);
+                if let Some(message) = status {
+                    status_ui(f, chunks[2], message, theme);
+                }
+                match data {
+                    ViewData::Meters(meters) => {
+                        meters_ui(f, body, meters, selected, layout, config, theme)
+                    }
+                    ViewData::Cards(cards) => cards_ui(f, body, cards, selected, theme),
+                    ViewData::Latency(rows) => latency_ui(f, body, rows, selected, theme),
+                    ViewData::Modules(modules) => modules_ui(f, body, modules, selected, theme),
+                    ViewData::Patchbay(graph) => {
+                        patchbay_ui(f, body, graph, selected, routing, theme)
+                    }
+                    ViewData::Applications(apps) => {
+                        applications_ui(f, body, apps, selected, layout, config, theme)
+                    }
+                    ViewData::Scenes(names) => scenes_ui(f, body, names, selected, theme),
+                }
+                match overlay {
+                    Some(Overlay::Help) => help_ui(f, &config.keybindings, theme),
+                    Some(Overlay::Palette(palette)) => palette_ui(f, palette, theme),
+                    Some(Overlay::Details(details)) => details_ui(f, details, theme),
+                    Some(Overlay::Confirm { prompt, .. }) => confirm_ui(f, prompt, theme),
+                    Some(Overlay::LoadModule(prompt)) => prompt_ui(f, prompt, theme),
+                    Some(Overlay::Wizard(wizard)) => wizard_ui(f, wizard, theme),
+                    None => {}
+                }
             })?;
         Ok(())
     }
@@ -101,3 +1183,16 @@ impl App {
     }
 }
 
+/// The daemon's name for the sink or sink input behind a meter, which has none for sources and
+/// recording streams
+fn target(kind: MeterKind, index: u32) -> Option<Target> {
+    let kind = match kind {
+        MeterKind::Sink => TargetKind::Sink,
+        MeterKind::SinkInput => TargetKind::SinkInput,
+        MeterKind::Source | MeterKind::SourceOutput => return None,
+    };
+    Some(Target {
+        kind,
+        selector: Selector::Index(index),
+    })
+}
@@ -0,0 +1,269 @@
+//! The detail pane: every property of the selected stream, device, card or application
+use std::io::{self, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use pulse::{format, proplist::Proplist, time::MicroSeconds, volume::ChannelVolumes};
+
+use tmix::{
+    data::{
+        CardInformation, PortInformation, SinkInformation, SinkInputInformation, SourceInformation,
+        SourceOutputInformation,
+    },
+    pulse_api::Application,
+};
+
+/// One line of the pane, a property and its value. Lines without a value head a section.
+pub(crate) struct Line {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+pub(crate) struct Details {
+    pub title: String,
+    pub lines: Vec<Line>,
+    /// Index of the first line shown
+    pub scroll: usize,
+    /// Whether the text was just copied, to say so in the title
+    pub copied: bool,
+}
+
+/// What the app should do after the pane handled a key
+pub(crate) enum Outcome {
+    Continue,
+    Close,
+}
+
+impl Details {
+    fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            lines: vec![],
+            scroll: 0,
+            copied: false,
+        }
+    }
+
+    fn field(&mut self, key: &str, value: impl ToString) {
+        self.lines.push(Line {
+            key: key.to_string(),
+            value: Some(value.to_string()),
+        });
+    }
+
+    fn optional(&mut self, key: &str, value: &Option<impl ToString>) {
+        if let Some(value) = value {
+            self.field(key, value.to_string());
+        }
+    }
+
+    fn section(&mut self, title: &str) {
+        self.lines.push(Line {
+            key: title.to_string(),
+            value: None,
+        });
+    }
+
+    fn volume(&mut self, volume: &ChannelVolumes) {
+        self.field("Volume", volume.print());
+    }
+
+    fn latency(&mut self, key: &str, micros: MicroSeconds) {
+        self.field(key, format!("{:.1} ms", micros.0 as f64 / 1000.0));
+    }
+
+    fn formats(&mut self, key: &str, formats: &[format::Info]) {
+        let formats: Vec<String> = formats.iter().map(|f| f.print()).collect();
+        self.field(key, formats.join(", "));
+    }
+
+    fn ports(&mut self, ports: &[PortInformation], active: &Option<PortInformation>) {
+        let name = |port: &PortInformation| {
+            port.description
+                .clone()
+                .or_else(|| port.name.clone())
+                .unwrap_or_default()
+        };
+        self.optional("Active port", &active.as_ref().map(name));
+        if !ports.is_empty() {
+            let ports: Vec<String> = ports.iter().map(name).collect();
+            self.field("Ports", ports.join(", "));
+        }
+    }
+
+    /// Every property in the list, sorted by name
+    fn properties(&mut self, proplist: &Proplist) {
+        self.section("Properties");
+        let mut keys: Vec<String> = proplist.iter().collect();
+        keys.sort();
+        for key in keys {
+            let value = proplist.get_str(&key).unwrap_or_else(|| {
+                proplist
+                    .get(&key)
+                    .map(|b| String::from_utf8_lossy(b).into())
+                    .unwrap_or_default()
+            });
+            self.field(&key, value);
+        }
+    }
+
+    pub fn sink(sink: &SinkInformation) -> Self {
+        let title = sink.description.clone().or_else(|| sink.name.clone());
+        let mut details = Self::new(title.unwrap_or_else(|| format!("Sink {}", sink.index)));
+        details.field("Index", sink.index);
+        details.optional("Name", &sink.name);
+        details.optional("Driver", &sink.driver);
+        details.field("Sample spec", sink.sample_spec.print());
+        details.field("Channel map", sink.channel_map.print());
+        details.volume(&sink.volume);
+        details.field("Muted", sink.mute);
+        details.latency("Latency", sink.latency);
+        details.latency("Configured latency", sink.configured_latency);
+        details.optional("Monitor source", &sink.monitor_source_name);
+        let flags: Vec<String> = sink
+            .flags
+            .iter_names()
+            .map(|(name, _)| name.to_lowercase())
+            .collect();
+        details.field("Flags", flags.join(", "));
+        details.formats("Formats", &sink.formats);
+        details.ports(&sink.ports, &sink.active_port);
+        details.optional("Card", &sink.card);
+        details.properties(&sink.proplist);
+        details
+    }
+
+    pub fn sink_input(input: &SinkInputInformation) -> Self {
+        let title = input.name.clone();
+        let mut details = Self::new(title.unwrap_or_else(|| format!("Stream {}", input.index)));
+        details.field("Index", input.index);
+        details.field("Sink", input.sink);
+        details.optional("Client", &input.client);
+        details.optional("Driver", &input.driver);
+        details.field("Sample spec", input.sample_spec.print());
+        details.field("Channel map", input.channel_map.print());
+        details.field("Format", input.format.print());
+        details.optional("Resample method", &input.resample_method);
+        details.volume(&input.volume);
+        details.field("Muted", input.mute);
+        details.field("Corked", input.corked);
+        details.latency("Buffer latency", input.buffer_usec);
+        details.latency("Sink latency", input.sink_usec);
+        details.properties(&input.proplist);
+        details
+    }
+
+    pub fn source(source: &SourceInformation) -> Self {
+        let title = source.description.clone().or_else(|| source.name.clone());
+        let mut details = Self::new(title.unwrap_or_else(|| format!("Source {}", source.index)));
+        details.field("Index", source.index);
+        details.optional("Name", &source.name);
+        details.optional("Driver", &source.driver);
+        details.volume(&source.volume);
+        details.field("Muted", source.mute);
+        details.optional("Monitor of sink", &source.monitor_of_sink);
+        details.ports(&source.ports, &source.active_port);
+        details.optional("Card", &source.card);
+        details.properties(&source.proplist);
+        details
+    }
+
+    pub fn source_output(output: &SourceOutputInformation) -> Self {
+        let title = output.name.clone();
+        let mut details = Self::new(title.unwrap_or_else(|| format!("Stream {}", output.index)));
+        details.field("Index", output.index);
+        details.field("Source", output.source);
+        details.optional("Client", &output.client);
+        details.optional("Driver", &output.driver);
+        details.field("Sample spec", output.sample_spec.print());
+        details.field("Channel map", output.channel_map.print());
+        details.field("Format", output.format.print());
+        details.optional("Resample method", &output.resample_method);
+        details.volume(&output.volume);
+        details.field("Muted", output.mute);
+        details.field("Corked", output.corked);
+        details.latency("Buffer latency", output.buffer_usec);
+        details.latency("Source latency", output.source_usec);
+        details.properties(&output.proplist);
+        details
+    }
+
+    pub fn card(card: &CardInformation) -> Self {
+        let mut details = Self::new(card.display_name());
+        details.field("Index", card.index);
+        details.optional("Name", &card.name);
+        details.optional("Driver", &card.driver);
+        details.section("Profiles");
+        for profile in &card.profiles {
+            let name = profile.name.clone().unwrap_or_default();
+            let mut value = profile.description.clone().unwrap_or_default();
+            if card.active_profile.as_ref().map(|p| &p.name) == Some(&profile.name) {
+                value.push_str(" (active)");
+            }
+            if !profile.available {
+                value.push_str(" (unavailable)");
+            }
+            details.field(&name, value);
+        }
+        details.properties(&card.proplist);
+        details
+    }
+
+    pub fn application(app: &Application) -> Self {
+        let mut details = Self::new(app.name());
+        let inputs: Vec<String> = app.sink_inputs().map(|i| i.index.to_string()).collect();
+        let outputs: Vec<String> = app.source_outputs().map(|o| o.index.to_string()).collect();
+        details.field("Playback streams", inputs.join(", "));
+        details.field("Recording streams", outputs.join(", "));
+        for client in app.clients() {
+            details.section(&format!("Client {}", client.index));
+            details.optional("Name", &client.name);
+            details.optional("Driver", &client.driver);
+            details.properties(&client.proplist);
+        }
+        details
+    }
+
+    /// The whole pane as plain text, one `key: value` per line
+    pub fn text(&self) -> String {
+        let lines: Vec<String> = self
+            .lines
+            .iter()
+            .map(|line| match &line.value {
+                Some(value) => format!("{}: {value}", line.key),
+                None => format!("[{}]", line.key),
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let last = self.lines.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('i') => return Outcome::Close,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Outcome::Close
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll = (self.scroll + 1).min(last),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::PageDown => self.scroll = (self.scroll + 10).min(last),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll = 0,
+            KeyCode::End | KeyCode::Char('G') => self.scroll = last,
+            KeyCode::Char('y') => match copy_to_clipboard(&self.text()) {
+                Ok(()) => self.copied = true,
+                Err(e) => log::warn!("Failed to copy the details: {e}"),
+            },
+            _ => {}
+        }
+        Outcome::Continue
+    }
+}
+
+/// Put text on the terminal's clipboard with an OSC 52 escape sequence, which also works over
+/// SSH. Terminals that don't support it ignore the sequence.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
@@ -0,0 +1,116 @@
+//! The latency tab: how the latency of each sink and its streams changes over time
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use tmix::{data::CardInformation, pulse_api::VolumeInfo};
+
+use super::tab::MeterKind;
+
+/// Samples kept for each sink and stream
+const HISTORY: usize = 120;
+/// How often latencies are sampled, however often the tab is redrawn
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+/// A playing stream with less audio than this buffered is likely to run dry
+const UNDERRUN_THRESHOLD_USEC: u64 = 5_000;
+
+/// The port a sink plays through, whose latency offset can be changed
+pub(crate) struct Port {
+    pub card: String,
+    pub port: String,
+    /// Offset in microseconds
+    pub offset: i64,
+}
+
+/// One row of the latency tab
+pub(crate) struct LatencyRow {
+    pub title: String,
+    pub kind: MeterKind,
+    /// Latency in microseconds, the most recent sample last
+    pub history: Vec<u64>,
+    /// The sink's active port, when it belongs to a card
+    pub port: Option<Port>,
+    /// Whether the stream is playing with so little buffered that it may run dry
+    pub underrun_prone: bool,
+}
+
+impl LatencyRow {
+    pub fn latency(&self) -> u64 {
+        self.history.last().copied().unwrap_or_default()
+    }
+}
+
+/// Latency samples of every sink and stream seen while the tab is open
+#[derive(Default)]
+pub(crate) struct LatencyHistory {
+    samples: HashMap<(MeterKind, u32), VecDeque<u64>>,
+    last_sample: Option<Instant>,
+}
+
+impl LatencyHistory {
+    /// Sample the latencies when it's time to, and lay out a row per sink followed by a row per
+    /// stream playing on it. Samples of sinks and streams that went away are dropped.
+    pub fn rows(&mut self, info: &VolumeInfo, cards: &[CardInformation]) -> Vec<LatencyRow> {
+        let sample = self
+            .last_sample
+            .is_none_or(|last| last.elapsed() >= SAMPLE_INTERVAL);
+        if sample {
+            self.last_sample = Some(Instant::now());
+        }
+
+        let mut seen = vec![];
+        let mut rows = vec![];
+        for (_, entry) in info.iter() {
+            let sink = entry.sink();
+            let port = sink.card.and_then(|index| {
+                let card = cards.iter().find(|c| c.index == index)?;
+                let port = sink.active_port.as_ref()?.name.clone()?;
+                Some(Port {
+                    card: card.name.clone()?,
+                    offset: card.port(&port)?.latency_offset,
+                    port,
+                })
+            });
+            let key = (MeterKind::Sink, sink.index);
+            seen.push(key);
+            rows.push(LatencyRow {
+                title: sink
+                    .description
+                    .clone()
+                    .or_else(|| sink.name.clone())
+                    .unwrap_or_default(),
+                kind: MeterKind::Sink,
+                history: self.record(key, sink.latency.0, sample),
+                port,
+                underrun_prone: false,
+            });
+
+            for input in entry.iter() {
+                let key = (MeterKind::SinkInput, input.index);
+                seen.push(key);
+                let latency = input.buffer_usec.0 + input.sink_usec.0;
+                rows.push(LatencyRow {
+                    title: format!("  {}", input.name.clone().unwrap_or_default()),
+                    kind: MeterKind::SinkInput,
+                    history: self.record(key, latency, sample),
+                    port: None,
+                    underrun_prone: !input.corked && input.buffer_usec.0 < UNDERRUN_THRESHOLD_USEC,
+                });
+            }
+        }
+        self.samples.retain(|key, _| seen.contains(key));
+        rows
+    }
+
+    fn record(&mut self, key: (MeterKind, u32), latency: u64, sample: bool) -> Vec<u64> {
+        let history = self.samples.entry(key).or_default();
+        if sample || history.is_empty() {
+            if history.len() == HISTORY {
+                history.pop_front();
+            }
+            history.push_back(latency);
+        }
+        history.iter().copied().collect()
+    }
+}
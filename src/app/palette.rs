@@ -0,0 +1,151 @@
+//! The `:` command palette, for running anything by typing part of its name
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use tmix::config::Action;
+
+/// Something the palette can run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Command {
+    Action(Action),
+    /// Move the given sink inputs onto a sink
+    MoveStreams {
+        inputs: Vec<u32>,
+        sink: u32,
+    },
+    SetDefaultSink(String),
+    ApplyScene(String),
+    /// Unload the module a virtual device was created with
+    RemoveVirtualDevice(u32),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    pub label: String,
+    pub command: Command,
+}
+
+impl Entry {
+    pub fn new(label: impl Into<String>, command: Command) -> Self {
+        Self {
+            label: label.into(),
+            command,
+        }
+    }
+}
+
+/// What the app should do after the palette handled a key
+pub(crate) enum Outcome {
+    Continue,
+    Close,
+    Run(Command),
+}
+
+pub(crate) struct Palette {
+    /// What the user typed so far
+    pub input: String,
+    entries: Vec<Entry>,
+    /// Indices of the entries matching the input, best match first
+    matches: Vec<usize>,
+    /// Index into `matches` of the highlighted entry
+    pub selected: usize,
+}
+
+impl Palette {
+    pub fn new(entries: Vec<Entry>) -> Self {
+        let mut palette = Self {
+            input: String::new(),
+            entries,
+            matches: vec![],
+            selected: 0,
+        };
+        palette.filter();
+        palette
+    }
+
+    /// The entries matching the input, best match first
+    pub fn matches(&self) -> impl Iterator<Item = &Entry> {
+        self.matches.iter().map(|i| &self.entries[*i])
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Outcome::Close,
+            KeyCode::Char('c') if ctrl => return Outcome::Close,
+            KeyCode::Enter => {
+                return match self.matches.get(self.selected) {
+                    Some(i) => Outcome::Run(self.entries[*i].command.clone()),
+                    None => Outcome::Close,
+                }
+            }
+            KeyCode::Up | KeyCode::BackTab => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('p') if ctrl => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.select_next(),
+            KeyCode::Char('n') if ctrl => self.select_next(),
+            // Complete the input to the highlighted entry
+            KeyCode::Tab => {
+                if let Some(i) = self.matches.get(self.selected) {
+                    self.input = self.entries[*i].label.clone();
+                    self.filter();
+                }
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.filter();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.input.push(c);
+                self.filter();
+            }
+            _ => {}
+        }
+        Outcome::Continue
+    }
+
+    fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.matches.len().saturating_sub(1));
+    }
+
+    fn filter(&mut self) {
+        self.selected = 0;
+        if self.input.trim().is_empty() {
+            self.matches = (0..self.entries.len()).collect();
+            return;
+        }
+        let mut scored: Vec<(i32, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| Some((fuzzy_score(&self.input, &entry.label)?, i)))
+            .collect();
+        // Stable, so equally good matches keep their order
+        scored.sort_by_key(|(score, _)| -score);
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+    }
+}
+
+/// How well `query` matches `text`, ignoring case, when every character of the query appears in
+/// the text in order. Matches that run together or start words score higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.chars().flat_map(char::to_lowercase) {
+        if q == ' ' {
+            continue;
+        }
+        let found = position + text[position..].iter().position(|c| *c == q)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(text[found - 1], ' ' | '_' | '-' | ':' | '.') {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    // Prefer shorter entries when the match is otherwise as good
+    Some(score * 100 - text.len() as i32)
+}
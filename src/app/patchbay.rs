@@ -0,0 +1,175 @@
+//! The patchbay tab: how audio is routed, as a graph of streams and devices
+//!
+//! Audio flows left to right through four columns: playback streams play on sinks, whose
+//! monitors are sources, which recording streams record from. A loopback records from a
+//! source and plays on a sink, so it shows up as a recording stream and a playback stream
+//! that name each other.
+use std::collections::HashMap;
+
+use tmix::pulse_api::{RecordingInfo, VolumeInfo};
+
+use super::tab::{device_title, stream_title, MeterKind};
+
+/// How many columns the graph has
+pub(crate) const COLUMNS: usize = 4;
+
+/// A stream or device of the graph
+pub(crate) struct Node {
+    pub kind: MeterKind,
+    pub index: u32,
+    pub title: String,
+    pub mute: bool,
+    /// The other end of a loopback, for its streams
+    pub loopback: Option<String>,
+}
+
+impl Node {
+    pub fn column(&self) -> usize {
+        match self.kind {
+            MeterKind::SinkInput => 0,
+            MeterKind::Sink => 1,
+            MeterKind::Source => 2,
+            MeterKind::SourceOutput => 3,
+        }
+    }
+}
+
+/// The routing graph. Nodes are ordered column by column, which is also the order they are
+/// selected in.
+pub(crate) struct Graph {
+    pub nodes: Vec<Node>,
+    /// Edges as indices into `nodes`, from the left column to the right one
+    pub edges: Vec<(usize, usize)>,
+}
+
+/// A stream picked up to be moved onto another device
+pub(crate) struct Routing {
+    pub kind: MeterKind,
+    pub index: u32,
+    pub title: String,
+}
+
+impl Graph {
+    /// Lay out the graph, keeping only the streams and devices that match the search filter.
+    /// Streams follow the order of the devices they are connected to, so few edges cross.
+    pub fn new(info: &VolumeInfo, recording: &RecordingInfo, filter: Option<&str>) -> Self {
+        let mut columns: [Vec<Node>; COLUMNS] = Default::default();
+        let mut edges = vec![];
+
+        // Which stream of a loopback module records, and which plays
+        let mut loopback_sources = HashMap::new();
+        for output in recording.source_outputs() {
+            if let (Some(module), Some(source)) =
+                (output.owner_module, recording.source(output.source))
+            {
+                let title = device_title(&source.description, &source.name, false);
+                loopback_sources.insert(module, title);
+            }
+        }
+        let mut loopback_sinks = HashMap::new();
+        for input in info.sink_inputs() {
+            if let (Some(module), Some(sink)) = (input.owner_module, info.sink(input.sink)) {
+                let title = device_title(&sink.description, &sink.name, false);
+                loopback_sinks.insert(module, title);
+            }
+        }
+
+        for (_, entry) in info.iter() {
+            let sink = entry.sink();
+            if filter.is_none_or(|f| sink.matches_text(f)) {
+                columns[1].push(Node {
+                    kind: MeterKind::Sink,
+                    index: sink.index,
+                    title: device_title(&sink.description, &sink.name, info.is_default(sink)),
+                    mute: sink.mute,
+                    loopback: None,
+                });
+            }
+            for input in entry.iter() {
+                if filter.is_none_or(|f| input.matches_text(f)) {
+                    columns[0].push(Node {
+                        kind: MeterKind::SinkInput,
+                        index: input.index,
+                        title: stream_title(&input.proplist, &input.name, input.index),
+                        mute: input.mute,
+                        loopback: input
+                            .owner_module
+                            .and_then(|m| loopback_sources.get(&m))
+                            .map(|source| format!("from {source}")),
+                    });
+                    edges.push((
+                        (MeterKind::SinkInput, input.index),
+                        (MeterKind::Sink, sink.index),
+                    ));
+                }
+            }
+        }
+
+        // Monitors first, in the order of their sinks, then the other sources
+        let mut sources: Vec<_> = recording.sources().collect();
+        sources.sort_by_key(|source| (source.monitor_of_sink.is_none(), source.monitor_of_sink));
+        for source in sources {
+            if filter.is_none_or(|f| source.matches_text(f)) {
+                columns[2].push(Node {
+                    kind: MeterKind::Source,
+                    index: source.index,
+                    title: device_title(
+                        &source.description,
+                        &source.name,
+                        recording.is_default(source),
+                    ),
+                    mute: source.mute,
+                    loopback: None,
+                });
+            }
+            if let Some(sink) = source.monitor_of_sink {
+                edges.push(((MeterKind::Sink, sink), (MeterKind::Source, source.index)));
+            }
+        }
+        let mut outputs: Vec<_> = recording.source_outputs().collect();
+        outputs.sort_by_key(|output| {
+            columns[2]
+                .iter()
+                .position(|node| node.index == output.source)
+        });
+        for output in outputs {
+            if filter.is_none_or(|f| output.matches_text(f)) {
+                columns[3].push(Node {
+                    kind: MeterKind::SourceOutput,
+                    index: output.index,
+                    title: stream_title(&output.proplist, &output.name, output.index),
+                    mute: output.mute,
+                    loopback: output
+                        .owner_module
+                        .and_then(|m| loopback_sinks.get(&m))
+                        .map(|sink| format!("to {sink}")),
+                });
+                edges.push((
+                    (MeterKind::Source, output.source),
+                    (MeterKind::SourceOutput, output.index),
+                ));
+            }
+        }
+
+        let nodes: Vec<Node> = columns.into_iter().flatten().collect();
+        let position = |(kind, index): (MeterKind, u32)| {
+            nodes
+                .iter()
+                .position(|node| node.kind == kind && node.index == index)
+        };
+        // Edges to nodes the filter left out are dropped
+        let edges = edges
+            .into_iter()
+            .filter_map(|(from, to)| Some((position(from)?, position(to)?)))
+            .collect();
+        Self { nodes, edges }
+    }
+
+    /// The nodes of a column, with their positions in `nodes`
+    pub fn column(&self, column: usize) -> impl Iterator<Item = (usize, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(move |(_, node)| node.column() == column)
+    }
+}
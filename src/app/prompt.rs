@@ -0,0 +1,40 @@
+//! A line of text typed into a popup, such as a module to load along with its arguments
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// What the app should do after the prompt handled a key
+pub(crate) enum Outcome {
+    Continue,
+    Close,
+    Submit(String),
+}
+
+pub(crate) struct Prompt {
+    pub title: String,
+    /// What the user typed so far
+    pub input: String,
+}
+
+impl Prompt {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            input: String::new(),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Outcome::Close,
+            KeyCode::Char('c') if ctrl => return Outcome::Close,
+            KeyCode::Enter => return Outcome::Submit(self.input.trim().to_string()),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char('u') if ctrl => self.input.clear(),
+            KeyCode::Char(c) if !ctrl => self.input.push(c),
+            _ => {}
+        }
+        Outcome::Continue
+    }
+}
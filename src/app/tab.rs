@@ -0,0 +1,315 @@
+//! The tabs of the mixer, laid out like pavucontrol's, and the meters they show
+use std::collections::HashSet;
+
+use pulse::{
+    proplist::{properties::APPLICATION_NAME, Proplist},
+    volume::ChannelVolumes,
+};
+
+use tmix::{
+    config::{Action, DefaultView},
+    data::{CardInformation, CardProfile, SinkInputInformation},
+    pulse_api::{RecordingInfo, VolumeInfo},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Tab {
+    /// Streams playing on the sinks
+    Playback,
+    /// Streams recording from the sources
+    Recording,
+    /// Sinks, each with the streams playing on it nested beneath
+    OutputDevices,
+    /// Sources, leaving out the monitors of sinks
+    InputDevices,
+    /// Cards and the profile each one uses
+    Configuration,
+    /// How the latency of the sinks and their streams changes over time
+    Latency,
+    /// Modules loaded into the server, to load more or unload them
+    Modules,
+    /// How streams and devices are connected, to move streams around
+    Patchbay,
+    /// Streams grouped by the application that owns them
+    Applications,
+    /// Saved scenes, to apply one
+    Scenes,
+}
+
+impl Tab {
+    /// Every tab, in the order they are shown and cycled through
+    pub const ALL: [Tab; 10] = [
+        Tab::Playback,
+        Tab::Recording,
+        Tab::OutputDevices,
+        Tab::InputDevices,
+        Tab::Configuration,
+        Tab::Latency,
+        Tab::Modules,
+        Tab::Patchbay,
+        Tab::Applications,
+        Tab::Scenes,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Tab::Playback => "Playback",
+            Tab::Recording => "Recording",
+            Tab::OutputDevices => "Output Devices",
+            Tab::InputDevices => "Input Devices",
+            Tab::Configuration => "Configuration",
+            Tab::Latency => "Latency",
+            Tab::Modules => "Modules",
+            Tab::Patchbay => "Patchbay",
+            Tab::Applications => "Applications",
+            Tab::Scenes => "Scenes",
+        }
+    }
+
+    /// The action that switches straight to this tab, if there is one
+    pub fn action(self) -> Option<Action> {
+        match self {
+            Tab::Playback => Some(Action::ShowPlayback),
+            Tab::Recording => Some(Action::ShowRecording),
+            Tab::OutputDevices => Some(Action::ShowOutputDevices),
+            Tab::InputDevices => Some(Action::ShowInputDevices),
+            Tab::Configuration => Some(Action::ShowConfiguration),
+            Tab::Latency => Some(Action::ShowLatency),
+            Tab::Modules => Some(Action::ShowModules),
+            Tab::Patchbay => Some(Action::ShowPatchbay),
+            Tab::Applications => Some(Action::ShowApplications),
+            Tab::Scenes => Some(Action::ShowScenes),
+        }
+    }
+
+    /// The tab an action switches to
+    pub fn for_action(action: Action) -> Option<Tab> {
+        Self::ALL
+            .into_iter()
+            .find(|tab| tab.action() == Some(action))
+    }
+
+    pub fn position(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|tab| *tab == self)
+            .expect("every tab is listed")
+    }
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self.position() + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        Self::ALL[(self.position() + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+impl From<DefaultView> for Tab {
+    fn from(value: DefaultView) -> Self {
+        match value {
+            DefaultView::Playback => Tab::Playback,
+            DefaultView::Recording => Tab::Recording,
+            DefaultView::OutputDevices => Tab::OutputDevices,
+            DefaultView::InputDevices => Tab::InputDevices,
+            DefaultView::Configuration => Tab::Configuration,
+            DefaultView::Latency => Tab::Latency,
+            DefaultView::Modules => Tab::Modules,
+            DefaultView::Patchbay => Tab::Patchbay,
+            DefaultView::Applications => Tab::Applications,
+            DefaultView::Scenes => Tab::Scenes,
+        }
+    }
+}
+
+/// What a meter controls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum MeterKind {
+    SinkInput,
+    SourceOutput,
+    Sink,
+    Source,
+}
+
+impl MeterKind {
+    /// The kind of device a stream of this kind can be moved to
+    pub fn target(self) -> Option<MeterKind> {
+        match self {
+            MeterKind::SinkInput => Some(MeterKind::Sink),
+            MeterKind::SourceOutput => Some(MeterKind::Source),
+            MeterKind::Sink | MeterKind::Source => None,
+        }
+    }
+}
+
+/// One meter of the stream and device tabs
+pub(crate) struct Meter {
+    pub title: String,
+    pub kind: MeterKind,
+    pub index: u32,
+    /// Name of the device, used to make it the default
+    pub name: Option<String>,
+    pub volume: ChannelVolumes,
+    pub mute: bool,
+    /// Whether the volume can be changed, which some streams don't allow
+    pub volume_writable: bool,
+    /// Sink a playback stream plays on, which it is nested under in the output devices tree
+    pub parent: Option<u32>,
+}
+
+/// The title of a device: its description, marked when it's the default
+pub(crate) fn device_title(
+    description: &Option<String>,
+    name: &Option<String>,
+    default: bool,
+) -> String {
+    let title = description
+        .clone()
+        .or_else(|| name.clone())
+        .unwrap_or_default();
+    match default {
+        true => format!("{title} (default)"),
+        false => title,
+    }
+}
+
+/// The title of a stream: the application that owns it, or the stream's own name
+pub(crate) fn stream_title(proplist: &Proplist, name: &Option<String>, index: u32) -> String {
+    proplist
+        .get_str(APPLICATION_NAME)
+        .or_else(|| name.clone())
+        .unwrap_or_else(|| format!("Stream {index}"))
+}
+
+/// A meter for every stream playing on a sink that matches the search filter
+pub(crate) fn playback_meters(info: &VolumeInfo, filter: Option<&str>) -> Vec<Meter> {
+    info.sink_inputs()
+        .filter(|input| filter.is_none_or(|f| input.matches_text(f)))
+        .map(|input| sink_input_meter(input, String::new()))
+        .collect()
+}
+
+/// A meter for a playback stream, its title following `prefix`
+fn sink_input_meter(input: &SinkInputInformation, prefix: String) -> Meter {
+    Meter {
+        title: prefix + &stream_title(&input.proplist, &input.name, input.index),
+        kind: MeterKind::SinkInput,
+        index: input.index,
+        name: input.name.clone(),
+        volume: input.volume,
+        mute: input.mute,
+        volume_writable: input.volume_writable,
+        parent: Some(input.sink),
+    }
+}
+
+/// A meter for every stream recording from a source that matches the search filter
+pub(crate) fn recording_meters(info: &RecordingInfo, filter: Option<&str>) -> Vec<Meter> {
+    info.source_outputs()
+        .filter(|output| filter.is_none_or(|f| output.matches_text(f)))
+        .map(|output| Meter {
+            title: stream_title(&output.proplist, &output.name, output.index),
+            kind: MeterKind::SourceOutput,
+            index: output.index,
+            name: output.name.clone(),
+            volume: output.volume,
+            mute: output.mute,
+            volume_writable: output.volume_writable,
+            parent: None,
+        })
+        .collect()
+}
+
+/// The output devices as a tree: a meter for every sink, each followed by the streams playing
+/// on it unless the sink is collapsed. With a search filter only matching sinks and streams are
+/// kept, along with the sinks the matching streams play on.
+pub(crate) fn output_meters(
+    info: &VolumeInfo,
+    filter: Option<&str>,
+    collapsed: &HashSet<u32>,
+) -> Vec<Meter> {
+    let mut meters = vec![];
+    for (_, entry) in info.iter() {
+        let sink = entry.sink();
+        let sink_matches = filter.is_none_or(|f| sink.matches_text(f));
+        let inputs: Vec<_> = entry
+            .iter()
+            .filter(|input| sink_matches || filter.is_some_and(|f| input.matches_text(f)))
+            .collect();
+        if !sink_matches && inputs.is_empty() {
+            continue;
+        }
+
+        let expanded = !collapsed.contains(&sink.index);
+        let mut title = match (inputs.is_empty(), expanded) {
+            (true, _) => "  ".to_string(),
+            (false, true) => "▾ ".to_string(),
+            (false, false) => "▸ ".to_string(),
+        };
+        title.push_str(&device_title(
+            &sink.description,
+            &sink.name,
+            info.is_default(sink),
+        ));
+        // Summarise the streams, which is all there is to see of them while collapsed
+        let muted = inputs.iter().filter(|input| input.mute).count();
+        match (inputs.len(), muted) {
+            (0, _) => {}
+            (1, 0) => title.push_str(" [1 stream]"),
+            (n, 0) => title.push_str(&format!(" [{n} streams]")),
+            (n, muted) => title.push_str(&format!(" [{n} streams, {muted} muted]")),
+        }
+
+        meters.push(Meter {
+            title,
+            kind: MeterKind::Sink,
+            index: sink.index,
+            name: sink.name.clone(),
+            volume: sink.volume,
+            mute: sink.mute,
+            volume_writable: true,
+            parent: None,
+        });
+        if expanded {
+            let last = inputs.len().saturating_sub(1);
+            meters.extend(inputs.into_iter().enumerate().map(|(i, input)| {
+                let branch = if i == last { "  └ " } else { "  ├ " };
+                sink_input_meter(input, branch.to_string())
+            }));
+        }
+    }
+    meters
+}
+
+/// A meter for every source that matches the search filter, leaving out the monitors of sinks
+pub(crate) fn input_meters(info: &RecordingInfo, filter: Option<&str>) -> Vec<Meter> {
+    info.sources()
+        .filter(|source| source.monitor_of_sink.is_none())
+        .filter(|source| filter.is_none_or(|f| source.matches_text(f)))
+        .map(|source| Meter {
+            title: device_title(&source.description, &source.name, info.is_default(source)),
+            kind: MeterKind::Source,
+            index: source.index,
+            name: source.name.clone(),
+            volume: source.volume,
+            mute: source.mute,
+            volume_writable: true,
+            parent: None,
+        })
+        .collect()
+}
+
+/// The available profile `step` places away from the card's active one, wrapping around
+pub(crate) fn adjacent_profile(card: &CardInformation, step: isize) -> Option<&CardProfile> {
+    let profiles: Vec<&CardProfile> = card.profiles.iter().filter(|p| p.available).collect();
+    if profiles.is_empty() {
+        return None;
+    }
+    let active = card.active_profile.as_ref().map(|p| &p.name);
+    let current = profiles
+        .iter()
+        .position(|p| Some(&p.name) == active)
+        .unwrap_or(0) as isize;
+    let len = profiles.len() as isize;
+    Some(profiles[(current + step).rem_euclid(len) as usize])
+}
@@ -1,92 +1,701 @@
 //! UI Functions
 
-use pulse::volume::VolumeLinear;
-use tmix::{pulse_api::VolumeInfo};
+use tmix::{
+    config::{Config, Keybindings, MeterLayout, Theme},
+    data::{CardInformation, ModuleInformation, MAX_PERCENT},
+    pulse_api::Application,
+    virtual_device::Tracked,
+};
 use tui::{
     backend::Backend,
     buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::{Modifier, Style},
     symbols,
-    widgets::{Block, Borders, Widget},
+    text::{Span, Spans},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Sparkline, Table,
+        TableState, Tabs, Widget,
+    },
     Frame,
 };
 
-pub(crate) fn ui<B: Backend>(f: &mut Frame<B>, data: VolumeInfo) {
-    let chunks = Layout::default()
+use super::{
+    details::Details,
+    latency::LatencyRow,
+    palette::Palette,
+    patchbay::{self, Graph, Routing},
+    prompt::Prompt,
+    tab::{Meter, MeterKind, Tab},
+    wizard::{Input, Wizard},
+};
+
+/// Split the screen into the configured number of meter columns
+fn columns(area: Rect, config: &Config) -> Vec<Rect> {
+    let columns = config.general.columns;
+    Layout::default()
         .direction(Direction::Horizontal)
-        .margin(1)
-        .constraints(
-            [
-                // Eventually We will want to be able to handle more than 5 sliders
-                // But I think we will alwyas only want 5 on screen at a time.
-                Constraint::Percentage(20),
-                Constraint::Percentage(20),
-                Constraint::Percentage(20),
-                Constraint::Percentage(20),
-                Constraint::Percentage(20),
-            ]
-            .as_ref(),
+        .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+        .split(area)
+}
+
+/// A bordered block in the theme's colours
+fn themed_block<'a>(title: String, focused: bool, theme: &Theme) -> Block<'a> {
+    let border = match focused {
+        true => Style::default()
+            .fg(theme.focused_border)
+            .add_modifier(Modifier::BOLD),
+        false => Style::default().fg(theme.border),
+    };
+    Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .style(Style::default().fg(theme.text).bg(theme.background))
+        .border_style(border)
+}
+
+/// The row of tabs, each labelled with the key that switches to it
+pub(crate) fn tabs_ui<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    current: Tab,
+    keybindings: &Keybindings,
+    theme: &Theme,
+) {
+    let titles = Tab::ALL
+        .iter()
+        .map(|tab| {
+            let key = tab
+                .action()
+                .and_then(|action| keybindings.iter().find(|(a, _)| *a == action))
+                .and_then(|(_, keys)| keys.first());
+            match key {
+                Some(key) => Spans::from(format!("{key} {}", tab.title())),
+                None => Spans::from(tab.title()),
+            }
+        })
+        .collect();
+    let tabs = Tabs::new(titles)
+        .select(current.position())
+        .style(Style::default().fg(theme.text))
+        .highlight_style(
+            Style::default()
+                .fg(theme.focused_border)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED),
         )
-        .split(f.size());
-    for (chunk, (i, info)) in data.iter().enumerate() {
+        .divider(symbols::line::VERTICAL);
+    f.render_widget(tabs, area);
+}
+
+/// A message along the bottom of the screen, about something that went wrong
+pub(crate) fn status_ui<B: Backend>(f: &mut Frame<B>, area: Rect, message: &str, theme: &Theme) {
+    let text = Paragraph::new(Span::styled(message, Style::default().fg(theme.peak)));
+    f.render_widget(text, area);
+}
+
+/// What a meter shows, in either layout
+struct Level {
+    title: String,
+    percent: f64,
+    mute: bool,
+}
+
+/// Draw meters in the chosen layout, scrolling so the selected meter stays on screen
+fn levels_ui<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    levels: &[Level],
+    selected: usize,
+    layout: MeterLayout,
+    config: &Config,
+    theme: &Theme,
+) {
+    if levels.is_empty() {
+        f.render_widget(themed_block("Nothing here".to_string(), false, theme), area);
+        return;
+    }
+    match layout {
+        MeterLayout::Vertical => {
+            let chunks = columns(area, config);
+            let first = selected.saturating_sub(chunks.len() - 1);
+            for (chunk, (i, level)) in chunks.iter().zip(levels.iter().enumerate().skip(first)) {
+                let bar = VolumeMeter::new(theme)
+                    .block(themed_block(level.title.clone(), i == selected, theme))
+                    .muted(level.mute)
+                    .value(level.percent as u16);
+                f.render_widget(bar, *chunk);
+            }
+        }
+        MeterLayout::Horizontal => {
+            let block = themed_block(String::new(), false, theme);
+            let inner = block.inner(area);
+            f.render_widget(block, area);
+            let rows = inner.height as usize;
+            let first = (selected + 1).saturating_sub(rows);
+            for (row, (i, level)) in levels.iter().enumerate().skip(first).take(rows).enumerate() {
+                let row_area = Rect::new(inner.x, inner.y + row as u16, inner.width, 1);
+                let meter = MeterRow::new(&level.title, theme)
+                    .selected(i == selected)
+                    .muted(level.mute)
+                    .value(level.percent as u16);
+                f.render_widget(meter, row_area);
+            }
+        }
+    }
+}
 
-        let sink_volume = Into::<VolumeLinear>::into(info.sink().volume.avg()).0;
+/// Draw a tab's meters
+pub(crate) fn meters_ui<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    meters: &[Meter],
+    selected: usize,
+    layout: MeterLayout,
+    config: &Config,
+    theme: &Theme,
+) {
+    let levels: Vec<Level> = meters
+        .iter()
+        .map(|meter| Level {
+            title: meter.title.clone(),
+            percent: config.general.scale.to_percent(meter.volume.avg()),
+            mute: meter.mute,
+        })
+        .collect();
+    levels_ui(f, area, &levels, selected, layout, config, theme);
+}
 
-        let block = Block::default()
-            .title(format!(
-                "{}",
-                info.sink().name.as_ref().unwrap_or(&format!("Window {i}"))
-            ))
-            .borders(Borders::ALL);
-        let bar = VolumeMeter::default()
-            .block(block)
-            .value((sink_volume * 100.0) as u8);
-        f.render_widget(bar, *chunks.get(chunk).expect("Testing for now"));
+/// List the cards along with their active profile, with the selected card highlighted
+pub(crate) fn cards_ui<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    cards: &[CardInformation],
+    selected: usize,
+    theme: &Theme,
+) {
+    let items: Vec<ListItem> = cards
+        .iter()
+        .map(|card| {
+            let profile = card
+                .active_profile
+                .as_ref()
+                .and_then(|p| p.description.clone().or_else(|| p.name.clone()))
+                .unwrap_or_else(|| "no profile".to_string());
+            ListItem::new(vec![
+                Spans::from(Span::styled(
+                    card.display_name(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )),
+                Spans::from(format!("  Profile: {profile}")),
+            ])
+        })
+        .collect();
+    let list = List::new(items)
+        .block(themed_block(
+            "Cards - volume keys switch the profile".to_string(),
+            true,
+            theme,
+        ))
+        .highlight_style(
+            Style::default()
+                .fg(theme.focused_border)
+                .add_modifier(Modifier::REVERSED),
+        )
+        .highlight_symbol("> ");
+    let mut state = ListState::default();
+    state.select(Some(selected));
+    f.render_stateful_widget(list, area, &mut state);
+}
 
-        let mut count = chunk + 1;
+/// A row per loaded module: its index, name, arguments and how often it's used
+pub(crate) fn modules_ui<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    modules: &[ModuleInformation],
+    selected: usize,
+    theme: &Theme,
+) {
+    let rows: Vec<Row> = modules
+        .iter()
+        .map(|module| {
+            let used = module.n_used.map(|n| n.to_string()).unwrap_or_default();
+            let mut name = module.name.clone().unwrap_or_default();
+            if let Some(device) = Tracked::from_module(module) {
+                name.push_str(&format!(" (tmix {})", device.kind));
+            }
+            Row::new(vec![
+                module.index.to_string(),
+                name,
+                module.argument.clone().unwrap_or_default(),
+                used,
+            ])
+        })
+        .collect();
+    let header = Row::new(vec!["Index", "Name", "Arguments", "Used"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let table = Table::new(rows)
+        .header(header)
+        .block(themed_block(
+            "Modules - a load, x unload".to_string(),
+            true,
+            theme,
+        ))
+        .widths(&[
+            Constraint::Length(6),
+            Constraint::Length(32),
+            Constraint::Min(10),
+            Constraint::Length(4),
+        ])
+        .column_spacing(2)
+        .highlight_style(
+            Style::default()
+                .fg(theme.focused_border)
+                .add_modifier(Modifier::REVERSED),
+        );
+    let mut state = TableState::default();
+    state.select(Some(selected));
+    f.render_stateful_widget(table, area, &mut state);
+}
 
-        for input in info.iter() {
-            let input_volume = (Into::<VolumeLinear>::into(input.volume.avg()).0 * 100.0) * sink_volume;
-            let block = Block::default()
-                .title(format!(
-                    "{}",
-                    input.name.as_ref().unwrap_or(&format!("Window {i}"))
-                ))
-                .borders(Borders::ALL);
-            let bar = VolumeMeter::default()
-                .block(block)
-                .value(input_volume as u8);
-            f.render_widget(bar, *chunks.get(count).expect("Testing for now"));
-            count += 1;
+/// The routing graph, titled with how to move a stream or with the stream being moved
+pub(crate) fn patchbay_ui<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    graph: &Graph,
+    selected: usize,
+    routing: Option<&Routing>,
+    theme: &Theme,
+) {
+    let title = match routing {
+        Some(routing) => {
+            let device = match routing.kind {
+                MeterKind::SourceOutput => "source",
+                _ => "sink",
+            };
+            format!(
+                "Patchbay - moving {}: select a {device} and press enter, esc to cancel",
+                routing.title
+            )
         }
+        None => "Patchbay - enter on a stream to move it".to_string(),
+    };
+    let block = themed_block(title, false, theme);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let widget = PatchbayGraph {
+        graph,
+        selected,
+        routing,
+        theme: *theme,
+    };
+    f.render_widget(widget, inner);
+}
 
+/// One row per sink and stream: the name, the current latency, a plot of its recent history
+/// and the latency offset of the sink's port. Streams that may run dry are drawn in the peak
+/// colour.
+pub(crate) fn latency_ui<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    rows: &[LatencyRow],
+    selected: usize,
+    theme: &Theme,
+) {
+    let block = themed_block(
+        "Latency - volume keys change the port offset".to_string(),
+        false,
+        theme,
+    );
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    const TITLE: u16 = 30;
+    const LATENCY: u16 = 12;
+    const OFFSET: u16 = 14;
+    if inner.width < TITLE + LATENCY + OFFSET + 10 {
+        return;
     }
+    let plot_width = inner.width - TITLE - LATENCY - OFFSET;
+
+    let visible = inner.height as usize;
+    let first = (selected + 1).saturating_sub(visible);
+    for (line, (i, row)) in rows
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .enumerate()
+    {
+        let y = inner.y + line as u16;
+        let color = match row.underrun_prone {
+            true => theme.peak,
+            false => theme.text,
+        };
+        let title_style = match i == selected {
+            true => Style::default()
+                .fg(theme.focused_border)
+                .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            false => Style::default().fg(color),
+        };
+        let mut title = row.title.clone();
+        if row.underrun_prone {
+            title.push_str(" !");
+        }
+        let text = |text: String, style: Style| Paragraph::new(Span::styled(text, style));
+        f.render_widget(
+            text(title, title_style),
+            Rect::new(inner.x, y, TITLE - 1, 1),
+        );
+        f.render_widget(
+            text(
+                format!("{:>9.1} ms", row.latency() as f64 / 1000.0),
+                Style::default().fg(color),
+            ),
+            Rect::new(inner.x + TITLE, y, LATENCY, 1),
+        );
+        if let Some(port) = &row.port {
+            f.render_widget(
+                text(
+                    format!("{:+.0} ms", port.offset as f64 / 1000.0),
+                    Style::default().fg(theme.ticks),
+                ),
+                Rect::new(inner.x + TITLE + LATENCY + plot_width, y, OFFSET, 1),
+            );
+        }
+
+        // Only the most recent samples that fit are plotted
+        let skip = row.history.len().saturating_sub(plot_width as usize - 2);
+        let plot = Sparkline::default()
+            .data(&row.history[skip..])
+            .style(Style::default().fg(match row.kind {
+                MeterKind::Sink => theme.thumb,
+                _ => color,
+            }));
+        f.render_widget(
+            plot,
+            Rect::new(inner.x + TITLE + LATENCY + 1, y, plot_width - 2, 1),
+        );
+    }
+}
+
+/// Draw one meter per application
+pub(crate) fn applications_ui<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    apps: &[Application],
+    selected: usize,
+    layout: MeterLayout,
+    config: &Config,
+    theme: &Theme,
+) {
+    let levels: Vec<Level> = apps
+        .iter()
+        .map(|app| {
+            let streams = app.sink_inputs().len() + app.source_outputs().len();
+            let mut title = format!("{} ({streams})", app.name());
+            if app.mute() && layout == MeterLayout::Vertical {
+                title.push_str(" [muted]");
+            }
+            Level {
+                title,
+                percent: config.general.scale.to_percent(app.volume()),
+                mute: app.mute(),
+            }
+        })
+        .collect();
+    levels_ui(f, area, &levels, selected, layout, config, theme);
+}
+
+/// List the saved scenes with the selected one highlighted
+pub(crate) fn scenes_ui<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    names: &[String],
+    selected: usize,
+    theme: &Theme,
+) {
+    if names.is_empty() {
+        let hint = "No scenes saved yet, save one with `tmix scene save <name>`";
+        let block = themed_block(hint.to_string(), false, theme);
+        f.render_widget(block, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = names.iter().map(|n| ListItem::new(n.as_str())).collect();
+    let list = List::new(items)
+        .block(themed_block("Apply a scene".to_string(), true, theme))
+        .highlight_style(
+            Style::default()
+                .fg(theme.focused_border)
+                .add_modifier(Modifier::REVERSED),
+        )
+        .highlight_symbol("> ");
+    let mut state = ListState::default();
+    state.select(Some(selected));
+    f.render_stateful_widget(list, area, &mut state);
 }
 
-#[derive(Debug, Default)]
+/// A rectangle of at most `width` by `height` in the middle of `area`
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// List every action along with the keys currently bound to it
+pub(crate) fn help_ui<B: Backend>(f: &mut Frame<B>, keybindings: &Keybindings, theme: &Theme) {
+    let rows: Vec<Row> = keybindings
+        .iter()
+        .filter(|(_, keys)| !keys.is_empty())
+        .map(|(action, keys)| {
+            let keys: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+            Row::new(vec![keys.join(", "), action.description().to_string()])
+        })
+        .collect();
+
+    let area = centered(f.size(), 60, rows.len() as u16 + 2);
+    let table = Table::new(rows)
+        .block(themed_block(
+            "Keys - press any key to close".to_string(),
+            true,
+            theme,
+        ))
+        .widths(&[Constraint::Length(16), Constraint::Min(10)])
+        .column_spacing(2);
+    f.render_widget(Clear, area);
+    f.render_widget(table, area);
+}
+
+/// The command palette: the input on the first line, matching commands below it
+pub(crate) fn palette_ui<B: Backend>(f: &mut Frame<B>, palette: &Palette, theme: &Theme) {
+    let size = f.size();
+    let area = centered(size, (size.width * 3 / 5).max(40), size.height * 3 / 5);
+    f.render_widget(Clear, area);
+    let block = themed_block("Command".to_string(), true, theme);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if inner.height < 2 {
+        return;
+    }
+
+    let input = Paragraph::new(Spans::from(vec![
+        Span::styled(":", Style::default().fg(theme.focused_border)),
+        Span::raw(palette.input.as_str()),
+    ]));
+    f.render_widget(input, Rect::new(inner.x, inner.y, inner.width, 1));
+    f.set_cursor(inner.x + 1 + palette.input.chars().count() as u16, inner.y);
+
+    let items: Vec<ListItem> = palette
+        .matches()
+        .map(|entry| ListItem::new(entry.label.as_str()))
+        .collect();
+    let list = List::new(items)
+        .style(Style::default().fg(theme.text))
+        .highlight_style(
+            Style::default()
+                .fg(theme.focused_border)
+                .add_modifier(Modifier::REVERSED),
+        );
+    let mut state = ListState::default();
+    state.select(Some(palette.selected));
+    let results = Rect::new(inner.x, inner.y + 1, inner.width, inner.height - 1);
+    f.render_stateful_widget(list, results, &mut state);
+}
+
+/// A question answered with y or n
+pub(crate) fn confirm_ui<B: Backend>(f: &mut Frame<B>, prompt: &str, theme: &Theme) {
+    let width = (prompt.chars().count() as u16 + 4).max(30);
+    let area = centered(f.size(), width, 4);
+    let text = Paragraph::new(vec![
+        Spans::from(prompt),
+        Spans::from(Span::styled(
+            "y to confirm, any other key to cancel",
+            Style::default().fg(theme.ticks),
+        )),
+    ])
+    .block(themed_block("Confirm".to_string(), true, theme));
+    f.render_widget(Clear, area);
+    f.render_widget(text, area);
+}
+
+/// A line of input in a popup, with a cursor after what was typed so far
+pub(crate) fn prompt_ui<B: Backend>(f: &mut Frame<B>, prompt: &Prompt, theme: &Theme) {
+    let width = (prompt.title.chars().count() as u16 + 4).max(60);
+    let area = centered(f.size(), width, 4);
+    let text = Paragraph::new(vec![
+        Spans::from(format!("{}█", prompt.input)),
+        Spans::from(Span::styled(
+            "enter to submit, esc to cancel",
+            Style::default().fg(theme.ticks),
+        )),
+    ])
+    .block(themed_block(prompt.title.clone(), true, theme));
+    f.render_widget(Clear, area);
+    f.render_widget(text, area);
+}
+
+/// The step of the virtual device wizard being filled in: a line of input, or a list to pick
+/// from
+pub(crate) fn wizard_ui<B: Backend>(f: &mut Frame<B>, wizard: &Wizard, theme: &Theme) {
+    let size = f.size();
+    let field = wizard.field();
+    let hint = Style::default().fg(theme.ticks);
+    let mut lines = vec![
+        Spans::from(Span::styled(
+            field.label,
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Spans::from(""),
+    ];
+    let keys = match &field.input {
+        Input::Text { value, default } => {
+            lines.push(Spans::from(vec![
+                Span::raw(format!("{value}█")),
+                Span::styled(
+                    match value.is_empty() && !default.is_empty() {
+                        true => format!(" {default}"),
+                        false => String::new(),
+                    },
+                    hint,
+                ),
+            ]));
+            "enter next, esc cancel"
+        }
+        Input::Pick {
+            choices,
+            cursor,
+            picked,
+            multiple,
+        } => {
+            for (i, (choice, picked)) in choices.iter().zip(picked).enumerate() {
+                let mark = match (multiple, picked) {
+                    (true, true) => "[x] ",
+                    (true, false) => "[ ] ",
+                    (false, _) => "",
+                };
+                let style = match i == *cursor {
+                    true => Style::default()
+                        .fg(theme.focused_border)
+                        .add_modifier(Modifier::REVERSED),
+                    false => Style::default(),
+                };
+                lines.push(Spans::from(Span::styled(
+                    format!("{mark}{}", choice.label),
+                    style,
+                )));
+            }
+            match multiple {
+                true => "j/k move, space pick, enter next, esc cancel",
+                false => "j/k move, enter pick, esc cancel",
+            }
+        }
+    };
+    lines.push(Spans::from(""));
+    match &wizard.error {
+        Some(error) => lines.push(Spans::from(Span::styled(
+            error.as_str(),
+            Style::default().fg(theme.peak),
+        ))),
+        None => lines.push(Spans::from(Span::styled(keys, hint))),
+    }
+
+    let area = centered(size, (size.width * 3 / 5).max(50), lines.len() as u16 + 2);
+    let text = Paragraph::new(lines).block(themed_block(wizard.title(), true, theme));
+    f.render_widget(Clear, area);
+    f.render_widget(text, area);
+}
+
+/// The detail pane, scrolled to its first shown line, with property names lined up
+pub(crate) fn details_ui<B: Backend>(f: &mut Frame<B>, details: &Details, theme: &Theme) {
+    let size = f.size();
+    let area = centered(size, size.width * 4 / 5, size.height * 4 / 5);
+    let status = match details.copied {
+        true => "copied",
+        false => "y copy",
+    };
+    let title = format!("{} - j/k scroll, {status}, esc close", details.title);
+    let key_width = details
+        .lines
+        .iter()
+        .filter(|line| line.value.is_some())
+        .map(|line| line.key.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(area.width as usize / 2);
+    let heading = Style::default()
+        .fg(theme.focused_border)
+        .add_modifier(Modifier::BOLD);
+    let lines: Vec<Spans> = details
+        .lines
+        .iter()
+        .map(|line| match &line.value {
+            Some(value) => Spans::from(vec![
+                Span::styled(
+                    format!("{:key_width$}  ", line.key),
+                    Style::default().fg(theme.ticks),
+                ),
+                Span::raw(value.as_str()),
+            ]),
+            None => Spans::from(Span::styled(line.key.as_str(), heading)),
+        })
+        .collect();
+    let pane = Paragraph::new(lines)
+        .block(themed_block(title, true, theme))
+        .scroll((details.scroll as u16, 0));
+    f.render_widget(Clear, area);
+    f.render_widget(pane, area);
+}
+
+#[derive(Debug)]
 struct VolumeMeter<'a> {
-    value: u8,
+    /// Volume in percent, which may go above 100
+    value: u16,
+    muted: bool,
     block: Option<Block<'a>>,
+    theme: Theme,
 }
 
 impl<'a> VolumeMeter<'a> {
+    /// Volume the meter reaches up to once boosted above 100%, the loudest tmix sets
+    const BOOST_MAX: u16 = MAX_PERCENT as u16;
+
+    pub fn new(theme: &Theme) -> Self {
+        Self {
+            value: 0,
+            muted: false,
+            block: None,
+            theme: *theme,
+        }
+    }
+
     /// Set the block to embed the VolumeMeter in
     pub fn block(mut self, b: Block<'a>) -> Self {
         self.block = Some(b);
         self
     }
 
-    /// Set the Value of the Meter, Values > 100 will be set to 100
-    pub fn value(mut self, val: u8) -> Self {
-        self.value = val;
+    /// Draw the thumb and label in the theme's muted colour
+    pub fn muted(mut self, muted: bool) -> Self {
+        self.muted = muted;
+        self
+    }
+
+    /// Set the Value of the Meter. Values above 100 extend the meter with a boost region, up
+    /// to 150.
+    pub fn value(mut self, val: u16) -> Self {
+        self.value = val.min(Self::BOOST_MAX);
         self
     }
 }
 
 impl<'a> Widget for VolumeMeter<'a> {
     fn render(mut self, area: Rect, buf: &mut Buffer) {
-        buf.set_style(area, Style::default().fg(Color::DarkGray));
+        let theme = self.theme;
+        buf.set_style(area, Style::default().fg(theme.track).bg(theme.background));
 
         // Get the Meter Area
         let meter_area = match self.block.take() {
@@ -98,52 +707,316 @@ impl<'a> Widget for VolumeMeter<'a> {
             None => area,
         };
 
-        // Not Enough space to draw
-        if meter_area.height < 2 {
+        // Not Enough space to draw, the last row holds the label
+        if meter_area.height < 3 || meter_area.width < 3 {
             return;
         }
 
         // Get the center of the cell
         let center = meter_area.left() + meter_area.width / 2;
-        let top = meter_area.top() + 1;
-        let bottom = meter_area.height - 1;
-        let value_pos = bottom - ((bottom - top) * self.value as u16 / 100);
+        let top = meter_area.top();
+        let bottom = meter_area.bottom() - 2;
+        let max = match self.value > 100 {
+            true => Self::BOOST_MAX,
+            false => 100,
+        };
+        let position = |value: u16| bottom - ((bottom - top) * value / max);
+        let value_pos = position(self.value);
+        let full_pos = position(100);
+
+        let thumb = match (self.muted, self.value > 100) {
+            (true, _) => theme.muted,
+            (false, true) => theme.peak,
+            (false, false) => theme.thumb,
+        };
 
         // Draw the Meter
         for vert in top..=bottom {
-            let mut fg_color = Color::DarkGray;
-            let symbol = if vert == value_pos {
-                fg_color = Color::Gray;
+            let track = match vert < full_pos {
+                true => theme.boost,
+                false => theme.track,
+            };
+            let (symbol, fg_color) = if vert == value_pos {
                 buf.get_mut(center - 1, vert)
                     .set_symbol(symbols::bar::FULL)
-                    .set_fg(fg_color);
+                    .set_fg(thumb);
                 buf.get_mut(center + 1, vert)
                     .set_symbol(symbols::bar::FULL)
-                    .set_fg(fg_color);
-                symbols::bar::FULL
+                    .set_fg(thumb);
+                (symbols::bar::FULL, thumb)
             } else if vert == top {
-                symbols::line::THICK.horizontal_down
+                (symbols::line::THICK.horizontal_down, track)
             } else if vert == bottom {
-                symbols::line::THICK.horizontal_up
+                (symbols::line::THICK.horizontal_up, track)
             }
             // TODO: Rather than do this modulo style, maybe we have it split in half until the
             // space between eahc split is < 5. Then use that as the distance spread.
             else if vert % (5) == 0 {
-                symbols::line::THICK.cross
+                (symbols::line::THICK.cross, theme.ticks)
             } else {
-                symbols::line::THICK.vertical
+                (symbols::line::THICK.vertical, track)
             };
             buf.get_mut(center, vert)
                 .set_symbol(symbol)
                 .set_fg(fg_color);
         }
 
+        let label = match self.muted {
+            true => Style::default().fg(theme.muted),
+            false => Style::default().fg(theme.text),
+        };
         buf.set_stringn(
             meter_area.left() + 1,
             meter_area.bottom() - 1,
             format!("{}%", self.value),
             5,
-            Style::default(),
+            label,
         );
     }
 }
+
+/// A meter drawn as a single row: the name, a bar, the percentage and whether it's muted
+#[derive(Debug)]
+struct MeterRow<'a> {
+    title: &'a str,
+    /// Volume in percent, which may go above 100
+    value: u16,
+    muted: bool,
+    selected: bool,
+    theme: Theme,
+}
+
+impl<'a> MeterRow<'a> {
+    /// Widest the name column gets
+    const MAX_TITLE: u16 = 30;
+
+    pub fn new(title: &'a str, theme: &Theme) -> Self {
+        Self {
+            title,
+            value: 0,
+            muted: false,
+            selected: false,
+            theme: *theme,
+        }
+    }
+
+    /// Highlight the name as the selected row
+    pub fn selected(mut self, selected: bool) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    pub fn muted(mut self, muted: bool) -> Self {
+        self.muted = muted;
+        self
+    }
+
+    /// Set the Value of the Meter, up to the boost maximum
+    pub fn value(mut self, val: u16) -> Self {
+        self.value = val.min(VolumeMeter::BOOST_MAX);
+        self
+    }
+}
+
+impl<'a> Widget for MeterRow<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme;
+        // Room for the percentage and the mute icon after the bar
+        const TAIL: u16 = 8;
+        if area.height < 1 || area.width < TAIL + 10 {
+            return;
+        }
+
+        let title_width = (area.width / 3).min(Self::MAX_TITLE);
+        let title_style = match self.selected {
+            true => Style::default()
+                .fg(theme.focused_border)
+                .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            false => Style::default().fg(theme.text),
+        };
+        buf.set_style(Rect::new(area.x, area.y, title_width, 1), title_style);
+        buf.set_stringn(
+            area.x,
+            area.y,
+            self.title,
+            title_width as usize - 1,
+            title_style,
+        );
+
+        // The bar always spans 0 to 150%, so rows can be compared at a glance
+        let bar_x = area.x + title_width;
+        let bar_width = area.width - title_width - TAIL;
+        let position = |value: u16| bar_x + bar_width * value / VolumeMeter::BOOST_MAX;
+        let value_pos = position(self.value);
+        let full_pos = position(100);
+        let fill = match (self.muted, self.value > 100) {
+            (true, _) => theme.muted,
+            (false, true) => theme.peak,
+            (false, false) => theme.thumb,
+        };
+        for x in bar_x..bar_x + bar_width {
+            let (symbol, color) = match (x < value_pos, x < full_pos) {
+                (true, _) => (symbols::block::FULL, fill),
+                (false, true) => (symbols::line::HORIZONTAL, theme.track),
+                (false, false) => (symbols::line::HORIZONTAL, theme.boost),
+            };
+            buf.get_mut(x, area.y).set_symbol(symbol).set_fg(color);
+        }
+
+        let label = match self.muted {
+            true => Style::default().fg(theme.muted),
+            false => Style::default().fg(theme.text),
+        };
+        let tail_x = bar_x + bar_width + 1;
+        buf.set_stringn(tail_x, area.y, format!("{:>4}%", self.value), 5, label);
+        if self.muted {
+            buf.set_stringn(tail_x + 6, area.y, "M", 1, label);
+        }
+    }
+}
+
+/// The routing graph: a row of column headings, a box per stream and device in each column,
+/// and the edges between them drawn in the gaps between the columns
+struct PatchbayGraph<'a> {
+    graph: &'a Graph,
+    selected: usize,
+    routing: Option<&'a Routing>,
+    theme: Theme,
+}
+
+impl PatchbayGraph<'_> {
+    /// Width of the gaps the edges are drawn in
+    const GAP: u16 = 7;
+    const NODE_HEIGHT: u16 = 3;
+    const HEADINGS: [&'static str; patchbay::COLUMNS] =
+        ["Playback", "Sinks", "Sources", "Recording"];
+
+    /// Draw an edge leaving a column at `y1`, turning in `lane` and arriving at `y2`, with an
+    /// arrow at `x1`
+    fn edge(buf: &mut Buffer, x0: u16, lane: u16, x1: u16, (y1, y2): (u16, u16), style: Style) {
+        for x in x0..lane {
+            buf.get_mut(x, y1)
+                .set_symbol(symbols::line::HORIZONTAL)
+                .set_style(style);
+        }
+        for y in y1.min(y2) + 1..y1.max(y2) {
+            buf.get_mut(lane, y)
+                .set_symbol(symbols::line::VERTICAL)
+                .set_style(style);
+        }
+        let (turn, arrive) = match y1.cmp(&y2) {
+            std::cmp::Ordering::Less => (symbols::line::TOP_RIGHT, symbols::line::BOTTOM_LEFT),
+            std::cmp::Ordering::Greater => (symbols::line::BOTTOM_RIGHT, symbols::line::TOP_LEFT),
+            std::cmp::Ordering::Equal => (symbols::line::HORIZONTAL, symbols::line::HORIZONTAL),
+        };
+        buf.get_mut(lane, y1).set_symbol(turn).set_style(style);
+        buf.get_mut(lane, y2).set_symbol(arrive).set_style(style);
+        for x in lane + 1..x1 {
+            buf.get_mut(x, y2)
+                .set_symbol(symbols::line::HORIZONTAL)
+                .set_style(style);
+        }
+        buf.get_mut(x1, y2).set_symbol("▶").set_style(style);
+    }
+}
+
+impl Widget for PatchbayGraph<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme;
+        let columns = patchbay::COLUMNS as u16;
+        if area.height < Self::NODE_HEIGHT + 1 || area.width < columns * 10 + 3 * Self::GAP {
+            return;
+        }
+        let width = (area.width - (columns - 1) * Self::GAP) / columns;
+        let column_x = |column: usize| area.x + column as u16 * (width + Self::GAP);
+        let top = area.y + 1;
+        let rows = ((area.height - 1) / Self::NODE_HEIGHT) as usize;
+
+        // Where each node is in its column, and the row its column is scrolled to so the
+        // selected node is shown
+        let mut position = vec![0; self.graph.nodes.len()];
+        let mut first = [0; patchbay::COLUMNS];
+        for (column, first) in first.iter_mut().enumerate() {
+            for (row, (i, _)) in self.graph.column(column).enumerate() {
+                position[i] = row;
+                if i == self.selected {
+                    *first = (row + 1).saturating_sub(rows);
+                }
+            }
+        }
+        let node_y = |i: usize| {
+            let row = position[i] as isize - first[self.graph.nodes[i].column()] as isize;
+            (row >= 0 && (row as usize) < rows).then(|| top + row as u16 * Self::NODE_HEIGHT)
+        };
+        // Edges to nodes scrolled out of view run to the top or bottom
+        let edge_y = |i: usize| match node_y(i) {
+            Some(y) => y + 1,
+            None if position[i] < first[self.graph.nodes[i].column()] => top,
+            None => area.bottom() - 1,
+        };
+
+        for (column, heading) in Self::HEADINGS.iter().enumerate() {
+            let style = Style::default()
+                .fg(theme.ticks)
+                .add_modifier(Modifier::BOLD);
+            buf.set_stringn(column_x(column), area.y, heading, width as usize, style);
+        }
+
+        // Edges of the selected node are drawn last, so they stay whole where edges cross
+        let mut edges = self.graph.edges.clone();
+        edges.sort_by_key(|(from, to)| *from == self.selected || *to == self.selected);
+        for (from, to) in edges {
+            let column = self.graph.nodes[from].column();
+            let x0 = column_x(column) + width;
+            let lanes = (Self::GAP - 3) as usize;
+            let lane = x0 + 1 + (position[to] % lanes) as u16;
+            let style = match from == self.selected || to == self.selected {
+                true => Style::default().fg(theme.focused_border),
+                false => Style::default().fg(theme.border),
+            };
+            let x1 = column_x(column + 1) - 1;
+            Self::edge(buf, x0, lane, x1, (edge_y(from), edge_y(to)), style);
+        }
+
+        for (i, node) in self.graph.nodes.iter().enumerate() {
+            let Some(y) = node_y(i) else {
+                continue;
+            };
+            let moving = self
+                .routing
+                .is_some_and(|r| r.kind == node.kind && r.index == node.index);
+            let target = self
+                .routing
+                .is_some_and(|r| Some(node.kind) == r.kind.target());
+            let border = match (i == self.selected, moving, target) {
+                (true, _, _) => Style::default()
+                    .fg(theme.focused_border)
+                    .add_modifier(Modifier::BOLD),
+                (false, true, _) => Style::default().fg(theme.peak),
+                (false, false, true) => Style::default().fg(theme.thumb),
+                (false, false, false) => Style::default().fg(theme.border),
+            };
+            let node_area = Rect::new(column_x(node.column()), y, width, Self::NODE_HEIGHT);
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border)
+                .render(node_area, buf);
+
+            let mut title = node.title.clone();
+            if let Some(loopback) = &node.loopback {
+                title.push_str(&format!(" ↺ {loopback}"));
+            }
+            let style = match node.mute {
+                true => Style::default().fg(theme.muted),
+                false => Style::default().fg(theme.text),
+            };
+            buf.set_stringn(
+                node_area.x + 1,
+                y + 1,
+                title,
+                width.saturating_sub(2) as usize,
+                style,
+            );
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! The guided flow creating a virtual device: pick a kind of device, then fill in its fields
+//! one at a time
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use tmix::virtual_device::{Kind, VirtualDevice, DEFAULT_LOOPBACK_LATENCY_MSEC};
+
+/// What the app should do after the wizard handled a key
+pub(crate) enum Outcome {
+    Continue,
+    Close,
+    Create(VirtualDevice),
+}
+
+/// Something a field can be set to, shown by its label
+#[derive(Clone)]
+pub(crate) struct Choice {
+    pub label: String,
+    /// What the field is set to, empty to leave the field out
+    pub value: String,
+}
+
+impl Choice {
+    pub fn new(label: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            value: value.into(),
+        }
+    }
+}
+
+pub(crate) enum Input {
+    /// Free text, with the value used when nothing was typed
+    Text { value: String, default: String },
+    /// One of the choices, or with `multiple` any number of them toggled with space
+    Pick {
+        choices: Vec<Choice>,
+        cursor: usize,
+        picked: Vec<bool>,
+        multiple: bool,
+    },
+}
+
+pub(crate) struct Field {
+    pub label: &'static str,
+    pub input: Input,
+}
+
+impl Field {
+    fn text(label: &'static str, default: &str) -> Self {
+        Self {
+            label,
+            input: Input::Text {
+                value: String::new(),
+                default: default.to_string(),
+            },
+        }
+    }
+
+    fn pick(label: &'static str, choices: Vec<Choice>, multiple: bool) -> Self {
+        Self {
+            label,
+            input: Input::Pick {
+                picked: vec![false; choices.len()],
+                choices,
+                cursor: 0,
+                multiple,
+            },
+        }
+    }
+
+    /// The text typed or the default, or the highlighted choice
+    fn value(&self) -> String {
+        match &self.input {
+            Input::Text { value, default } if value.trim().is_empty() => default.clone(),
+            Input::Text { value, .. } => value.trim().to_string(),
+            Input::Pick {
+                choices, cursor, ..
+            } => choices
+                .get(*cursor)
+                .map(|c| c.value.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The toggled choices of a field picking several
+    fn values(&self) -> Vec<String> {
+        match &self.input {
+            Input::Pick {
+                choices, picked, ..
+            } => choices
+                .iter()
+                .zip(picked)
+                .filter(|(_, picked)| **picked)
+                .map(|(c, _)| c.value.clone())
+                .collect(),
+            Input::Text { .. } => vec![],
+        }
+    }
+
+    /// The value, or `None` when it was left empty
+    fn optional(&self) -> Option<String> {
+        Some(self.value()).filter(|v| !v.is_empty())
+    }
+}
+
+pub(crate) struct Wizard {
+    /// The sinks and sources fields can pick from
+    sinks: Vec<Choice>,
+    sources: Vec<Choice>,
+    /// The kind of device, once picked on the first step
+    kind: Option<Kind>,
+    fields: Vec<Field>,
+    step: usize,
+    /// Why the device could not be created, shown until the next key press
+    pub error: Option<String>,
+}
+
+impl Wizard {
+    pub fn new(sinks: Vec<Choice>, sources: Vec<Choice>) -> Self {
+        let kinds = Kind::ALL
+            .iter()
+            .map(|kind| Choice::new(format!("{kind}: {}", kind.description()), kind.name()))
+            .collect();
+        Self {
+            sinks,
+            sources,
+            kind: None,
+            fields: vec![Field::pick("Kind of device", kinds, false)],
+            step: 0,
+            error: None,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self.kind {
+            Some(kind) => format!(
+                "New {kind} - step {} of {}",
+                self.step + 1,
+                self.fields.len()
+            ),
+            None => "New virtual device".to_string(),
+        }
+    }
+
+    /// The field being filled in
+    pub fn field(&self) -> &Field {
+        &self.fields[self.step]
+    }
+
+    /// The fields of a kind of device, with sensible defaults
+    fn fields(&self, kind: Kind) -> Vec<Field> {
+        let default = |label: &str| Choice::new(label, "");
+        let mut sources = vec![default("The default source")];
+        sources.extend(self.sources.iter().cloned());
+        let mut sinks = vec![default("The default sink")];
+        sinks.extend(self.sinks.iter().cloned());
+        match kind {
+            Kind::NullSink => vec![
+                Field::text("Sink name", "virtual"),
+                Field::text("Description, the sink name when left empty", ""),
+            ],
+            Kind::CombineSink => vec![
+                Field::text("Sink name", "combined"),
+                Field::pick(
+                    "Sinks to play on, every sink when none are picked",
+                    self.sinks.clone(),
+                    true,
+                ),
+                Field::text("Description, the sink name when left empty", ""),
+            ],
+            Kind::Loopback => vec![
+                Field::pick("Source to record from", sources, false),
+                Field::pick("Sink to play on", sinks, false),
+                Field::text(
+                    "Latency in milliseconds",
+                    &DEFAULT_LOOPBACK_LATENCY_MSEC.to_string(),
+                ),
+            ],
+            Kind::RemapSink => vec![
+                Field::text("Sink name", "remapped"),
+                Field::pick("Sink to play on", self.sinks.clone(), false),
+                Field::pick(
+                    "Channels",
+                    vec![
+                        Choice::new("Mono, downmixing every channel", "mono"),
+                        Choice::new("Stereo", "front-left,front-right"),
+                        Choice::new("Swapped stereo", "front-right,front-left"),
+                        Choice::new("The same as the sink", ""),
+                    ],
+                    false,
+                ),
+                Field::text("Description, the sink name when left empty", ""),
+            ],
+        }
+    }
+
+    /// The device described by the filled in fields
+    fn device(&self, kind: Kind) -> Result<VirtualDevice, String> {
+        let f = &self.fields;
+        let device = match kind {
+            Kind::NullSink => VirtualDevice::NullSink {
+                name: f[1].value(),
+                description: f[2].optional(),
+            },
+            Kind::CombineSink => VirtualDevice::CombineSink {
+                name: f[1].value(),
+                sinks: f[2].values(),
+                description: f[3].optional(),
+            },
+            Kind::Loopback => VirtualDevice::Loopback {
+                source: f[1].optional(),
+                sink: f[2].optional(),
+                latency_msec: f[3]
+                    .value()
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a number of milliseconds", f[3].value()))?,
+            },
+            Kind::RemapSink => VirtualDevice::RemapSink {
+                name: f[1].value(),
+                master: f[2].value(),
+                channel_map: f[3].optional(),
+                description: f[4].optional(),
+            },
+        };
+        Ok(device)
+    }
+
+    /// Go on to the next field, or finish on the last one
+    fn next(&mut self) -> Outcome {
+        let kind = match self.kind {
+            Some(kind) => kind,
+            None => {
+                let Ok(kind) = self.fields[0].value().parse() else {
+                    return Outcome::Continue;
+                };
+                self.kind = Some(kind);
+                let fields = self.fields(kind);
+                self.fields.extend(fields);
+                kind
+            }
+        };
+        if self.step + 1 < self.fields.len() {
+            self.step += 1;
+            return Outcome::Continue;
+        }
+        match self.device(kind) {
+            Ok(device) => Outcome::Create(device),
+            Err(e) => {
+                self.error = Some(e);
+                Outcome::Continue
+            }
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        self.error = None;
+        match key.code {
+            KeyCode::Esc => return Outcome::Close,
+            KeyCode::Char('c') if ctrl => return Outcome::Close,
+            KeyCode::Enter => return self.next(),
+            _ => {}
+        }
+        match &mut self.fields[self.step].input {
+            Input::Text { value, .. } => match key.code {
+                KeyCode::Backspace => {
+                    value.pop();
+                }
+                KeyCode::Char('u') if ctrl => value.clear(),
+                KeyCode::Char(c) if !ctrl => value.push(c),
+                _ => {}
+            },
+            Input::Pick {
+                choices,
+                cursor,
+                picked,
+                multiple,
+            } => match key.code {
+                KeyCode::Up | KeyCode::Char('k') => *cursor = cursor.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    *cursor = (*cursor + 1).min(choices.len().saturating_sub(1))
+                }
+                KeyCode::Char(' ') if *multiple => {
+                    if let Some(picked) = picked.get_mut(*cursor) {
+                        *picked = !*picked;
+                    }
+                }
+                _ => {}
+            },
+        }
+        Outcome::Continue
+    }
+}
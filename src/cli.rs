@@ -0,0 +1,773 @@
+//! Non-interactive commands for scripting TMIX
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use pulse::{
+    context::subscribe::{Facility, InterestMaskSet, Operation as SubscriptionOperation},
+    volume::Volume,
+};
+use tmix::{
+    config::Config,
+    control::{self, ControlError, MuteAction, Resolved, VolumeChange},
+    daemon::{self, Client, Request},
+    data::Scale,
+    pulse_api::{PulseAPI, VolumeInfo},
+    rules::{self, Rule},
+    scene::{Scene, SceneError},
+    status::{Icons, Status, Template},
+    target::{KillTarget, Selector, Target, TargetKind},
+    virtual_device::{self, VirtualDevice, VirtualError, DEFAULT_LOOPBACK_LATENCY_MSEC},
+    watch::ChangeEvent,
+};
+
+const EXIT_CODES: &str = "\
+Exit status:
+  0  success
+  1  the PulseAudio server could not be reached or rejected the change
+  2  invalid command line
+  3  no sink or stream matched the target
+  4  the config file or a scene could not be read or is invalid";
+
+/// Terminal mixer for PulseAudio. Launches the mixer when no command is given.
+#[derive(Debug, Parser)]
+#[command(name = "tmix", version, after_help = EXIT_CODES)]
+pub struct Cli {
+    /// Config file to use instead of $XDG_CONFIG_HOME/tmix/config.toml
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Talk to the PulseAudio server directly even when `tmix daemon` is running
+    #[arg(long, global = true)]
+    pub no_daemon: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Exit status for a config file that could not be loaded
+pub const CONFIG_ERROR: u8 = 4;
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List sinks and the streams playing on them
+    ///
+    /// The JSON and YAML output follows a versioned schema carrying a `schema_version` field.
+    List {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+        /// Shorthand for --format=json
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// Print the volume and mute state of a target
+    ///
+    /// Targets are written as [sink:|input:]<index|name>, or `default` for the default sink.
+    Get { target: Target },
+    /// Set the volume of a target to a percentage, or change it with a leading + or -, up to
+    /// the config's max_volume
+    SetVolume {
+        target: Target,
+        #[arg(allow_hyphen_values = true)]
+        value: VolumeChange,
+    },
+    /// Mute, unmute or toggle the mute state of a target
+    Mute {
+        target: Target,
+        #[arg(value_enum, default_value_t = MuteAction::Toggle)]
+        action: MuteAction,
+    },
+    /// Move a stream to another sink
+    Move {
+        #[arg(value_parser = Target::parse_sink_input)]
+        input: Target,
+        sink: Selector,
+    },
+    /// Make a sink the default sink
+    Default { sink: Selector },
+    /// Disconnect a stream, or a client along with all of its streams
+    ///
+    /// Targets are written as [input:|output:|client:]<index|name>, where names match the
+    /// stream or client name and the application name.
+    Kill { target: KillTarget },
+    /// Print a one line summary of a sink or stream, for status bars
+    ///
+    /// The format can use the fields {volume}, {mute}, {name}, {description}, {port} and
+    /// {icon}. Write {{ and }} for literal braces.
+    Status {
+        /// Sink or stream to show, the default sink when left out
+        target: Option<Target>,
+        /// Template for the line
+        #[arg(long, default_value = "{icon} {volume}%")]
+        format: Template,
+        /// Icons for {icon} as <percent>=<icon> thresholds, plus muted=<icon>
+        #[arg(long, default_value_t = Icons::default())]
+        icons: Icons,
+        /// Keep running and print a new line whenever the summary changes
+        #[arg(long, short)]
+        follow: bool,
+    },
+    /// Print a JSON line for every change to sinks and streams until interrupted
+    Watch {
+        /// Only report changes to this sink or stream
+        target: Option<Target>,
+    },
+    /// Keep one connection to the server open and accept commands on a local socket
+    ///
+    /// List, watch, and changes made with set-volume, mute, move, default, kill and scene go
+    /// through the daemon while it runs, as do changes made in the mixer. The daemon applies the
+    /// rules from the config file to new streams.
+    Daemon,
+    /// Save and apply named scenes of volumes, mute states, default devices and routing
+    Scene {
+        #[command(subcommand)]
+        command: SceneCommand,
+    },
+    /// Apply the rules from the config file to streams
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
+    /// Create virtual sinks and loopbacks, and remove them again
+    Virtual {
+        #[command(subcommand)]
+        command: VirtualCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SceneCommand {
+    /// List the saved scenes
+    List,
+    /// Save the current state as a scene, replacing any scene with the same name
+    Save { name: String },
+    /// Restore a saved scene
+    Apply { name: String },
+    /// Delete a saved scene
+    Delete { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RulesCommand {
+    /// Keep running and apply the rules to every new stream
+    Run {
+        /// Also apply the rules to the streams that are already playing
+        #[arg(long)]
+        existing: bool,
+    },
+    /// Show which rules would apply to the current streams, without changing anything
+    DryRun,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum VirtualCommand {
+    /// List the virtual devices created by tmix that are still loaded
+    List,
+    /// Create a sink that plays nowhere, e.g. to record its monitor for streaming
+    NullSink {
+        name: String,
+        /// Name shown in mixers, the sink name when left out
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Create a sink that plays on several sinks at once
+    CombineSink {
+        name: String,
+        /// Sinks to play on, every sink when left out
+        sinks: Vec<String>,
+        /// Name shown in mixers, the sink name when left out
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Play what a source records on a sink, e.g. a microphone on headphones
+    Loopback {
+        /// Source to record from, the default source when left out
+        #[arg(long)]
+        source: Option<String>,
+        /// Sink to play on, the default sink when left out
+        #[arg(long)]
+        sink: Option<String>,
+        /// Latency in milliseconds
+        #[arg(long, default_value_t = DEFAULT_LOOPBACK_LATENCY_MSEC)]
+        latency_msec: u32,
+    },
+    /// Create a sink that plays on another sink with its channels remapped
+    RemapSink {
+        name: String,
+        /// Sink to play on
+        master: String,
+        /// Channels of the new sink, like `mono` or `front-left,front-right`
+        #[arg(long)]
+        channel_map: Option<String>,
+        /// Name shown in mixers, the sink name when left out
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Remove a virtual device, given its module index or sink name
+    Remove {
+        #[arg(required_unless_present = "all")]
+        device: Option<Selector>,
+        /// Remove every virtual device created by tmix
+        #[arg(long, conflicts_with = "device")]
+        all: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Yaml,
+}
+
+#[derive(Debug, Error)]
+enum CliError {
+    #[error("no match for {0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Pulse(#[from] io::Error),
+    #[error("failed to write output: {0}")]
+    Output(String),
+    #[error(transparent)]
+    Scene(#[from] SceneError),
+    #[error(transparent)]
+    Virtual(#[from] VirtualError),
+    /// An error reported by the daemon, with the exit status to use
+    #[error("{0}")]
+    Daemon(String, u8),
+}
+
+impl From<ControlError> for CliError {
+    fn from(e: ControlError) -> Self {
+        match e {
+            ControlError::NotFound(target) => CliError::NotFound(target),
+            ControlError::NotAStream(_) => CliError::Daemon(e.to_string(), daemon::INVALID_REQUEST),
+            ControlError::Pulse(e) => CliError::Pulse(e),
+        }
+    }
+}
+
+impl CliError {
+    fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::Pulse(_) | CliError::Output(_) => ExitCode::from(1),
+            CliError::NotFound(_) => ExitCode::from(3),
+            CliError::Scene(e) => match e {
+                SceneError::InvalidName(_) => ExitCode::from(2),
+                SceneError::NotFound(_) => ExitCode::from(3),
+                SceneError::Pulse(_) => ExitCode::from(1),
+                _ => ExitCode::from(CONFIG_ERROR),
+            },
+            CliError::Virtual(e) => match e {
+                VirtualError::Invalid { .. } => ExitCode::from(2),
+                VirtualError::NotFound(_) => ExitCode::from(3),
+                VirtualError::Pulse(_) => ExitCode::from(1),
+            },
+            CliError::Daemon(_, code) => ExitCode::from(*code),
+        }
+    }
+}
+
+/// Run a command, returning the process exit status. Changes, listings and watches go through
+/// the daemon when one is running, and otherwise over a connection of our own.
+pub fn run(command: Command, no_daemon: bool, config: &Config) -> ExitCode {
+    let socket = config.daemon.socket_path();
+    let result = match command {
+        Command::Daemon => daemon::serve(config, &socket).map_err(CliError::from),
+        command => match remote_request(&command).filter(|_| !no_daemon) {
+            Some(request) => match Client::connect(&socket) {
+                Ok(client) => run_remote(client, &command, &request, config.general.scale),
+                Err(_) => run_local(command, config),
+            },
+            None => run_local(command, config),
+        },
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("tmix: {e}");
+            e.exit_code()
+        }
+    }
+}
+
+fn run_local(command: Command, config: &Config) -> Result<(), CliError> {
+    let mut api = PulseAPI::new();
+    api.set_timeouts(config.server.timeouts());
+    let result = api
+        .startup_connection(config.server.address.as_deref())
+        .map_err(CliError::from)
+        .and_then(|_| execute(&mut api, command, config));
+    api.shutdown();
+    result
+}
+
+/// The daemon request for a command that changes something, or that reads state kept by the
+/// daemon
+fn remote_request(command: &Command) -> Option<Request> {
+    let request = match command {
+        Command::List { .. } => Request::List,
+        Command::Get { target } => Request::Get {
+            target: target.clone(),
+        },
+        Command::SetVolume { target, value } => Request::Set {
+            target: target.clone(),
+            volume: *value,
+        },
+        Command::Mute { target, action } => Request::Mute {
+            target: target.clone(),
+            action: *action,
+        },
+        Command::Move { input, sink } => Request::Move {
+            input: input.clone(),
+            sink: sink.clone(),
+        },
+        Command::Default { sink } => Request::Default { sink: sink.clone() },
+        Command::Kill { target } => Request::Kill {
+            target: target.clone(),
+        },
+        Command::Scene {
+            command: SceneCommand::Save { name },
+        } => Request::SaveScene { name: name.clone() },
+        Command::Scene {
+            command: SceneCommand::Apply { name },
+        } => Request::ApplyScene { name: name.clone() },
+        Command::Watch { target } => Request::Subscribe {
+            target: target.clone(),
+        },
+        _ => return None,
+    };
+    Some(request)
+}
+
+fn run_remote(
+    mut client: Client,
+    command: &Command,
+    request: &Request,
+    scale: Scale,
+) -> Result<(), CliError> {
+    let response = client.request(request)?;
+    if !response.ok {
+        let error = response
+            .error
+            .unwrap_or_else(|| "unknown error".to_string());
+        return Err(CliError::Daemon(
+            error,
+            response.code.unwrap_or(daemon::SERVER_ERROR),
+        ));
+    }
+    match (command, response.data) {
+        (Command::Get { .. }, Some(Value::Array(volumes))) => {
+            for volume in volumes {
+                let percent = volume["volume"].as_f64().unwrap_or_default();
+                print_volume(percent, volume["mute"].as_bool().unwrap_or_default());
+            }
+        }
+        (Command::List { format, json }, Some(listing)) => {
+            let format = if *json { OutputFormat::Json } else { *format };
+            list(&listing, format, scale)?
+        }
+        (Command::Watch { .. }, _) => follow(client)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Print the change events a daemon sends after a `subscribe` request. The daemon keeps
+/// running when the server fails to answer in time, so the errors it reports are only warned
+/// about.
+fn follow(mut client: Client) -> Result<(), CliError> {
+    let mut stdout = io::stdout().lock();
+    loop {
+        let event = client.next_event()?;
+        if event["event"] == "error" {
+            log::warn!("{}", event["error"].as_str().unwrap_or("unknown error"));
+            continue;
+        }
+        writeln!(stdout, "{event}")
+            .and_then(|_| stdout.flush())
+            .map_err(|e| CliError::Output(e.to_string()))?;
+    }
+}
+
+fn execute(api: &mut PulseAPI, command: Command, config: &Config) -> Result<(), CliError> {
+    let scale = config.general.scale;
+    match command {
+        Command::List { format, json } => {
+            let format = if json { OutputFormat::Json } else { format };
+            let info = api.get_volume_info()?;
+            let listing =
+                serde_json::to_value(&info).map_err(|e| CliError::Output(e.to_string()))?;
+            list(&listing, format, scale)?
+        }
+        Command::Get { target } => control::volumes(&api.get_volume_info()?, &target, scale)?
+            .into_iter()
+            .for_each(|(percent, mute)| print_volume(percent, mute)),
+        Command::SetVolume { target, value } => {
+            let info = api.get_volume_info()?;
+            control::set_volume(api, &info, &target, value, scale, config.general.max_volume)?
+        }
+        Command::Mute { target, action } => {
+            let info = api.get_volume_info()?;
+            control::set_mute(api, &info, &target, action)?
+        }
+        Command::Move { input, sink } => {
+            let info = api.get_volume_info()?;
+            control::move_inputs(api, &info, &input, &sink)?
+        }
+        Command::Default { sink } => {
+            let info = api.get_volume_info()?;
+            control::set_default(api, &info, &sink)?
+        }
+        Command::Kill { target } => control::kill(api, &target)?,
+        Command::Status {
+            target,
+            format,
+            icons,
+            follow,
+        } => {
+            let target = target.unwrap_or(Target {
+                kind: TargetKind::Sink,
+                selector: Selector::Default,
+            });
+            let info = api.get_volume_info()?;
+            status(api, info, &target, &format, &icons, scale, follow)?
+        }
+        Command::Watch { target } => {
+            let info = api.get_volume_info()?;
+            watch(api, info, target.as_ref(), scale)?
+        }
+        Command::Daemon => unreachable!("the daemon makes its own connection"),
+        Command::Scene { command } => scene(api, command)?,
+        Command::Rules { command } => {
+            let info = api.get_volume_info()?;
+            match command {
+                RulesCommand::Run { existing } => {
+                    run_rules(api, info, &config.rules, scale, existing)?
+                }
+                RulesCommand::DryRun => dry_run_rules(&info, &config.rules),
+            }
+        }
+        Command::Virtual { command } => virtual_devices(api, command)?,
+    }
+    Ok(())
+}
+
+/// Render the target's status line, if the target exists
+fn status_line(
+    info: &VolumeInfo,
+    target: &Target,
+    format: &Template,
+    icons: &Icons,
+    scale: Scale,
+) -> Result<String, CliError> {
+    let status = match control::resolve(info, target)? {
+        Resolved::Sink(sink) => Status::from_sink(sink, scale),
+        Resolved::SinkInputs(inputs) => Status::from_sink_input(inputs[0], scale),
+    };
+    Ok(format.render(&status, icons))
+}
+
+/// Print the status line, and with `follow` a new one every time it changes. While following, a
+/// target that goes away is shown as an empty line rather than ending the command. Losing the
+/// server, or it no longer answering, ends the command with an empty line.
+fn status(
+    api: &mut PulseAPI,
+    info: VolumeInfo,
+    target: &Target,
+    format: &Template,
+    icons: &Icons,
+    scale: Scale,
+    follow: bool,
+) -> Result<(), CliError> {
+    if !follow {
+        println!("{}", status_line(&info, target, format, icons, scale)?);
+        return Ok(());
+    }
+
+    api.subscribe(InterestMaskSet::SINK | InterestMaskSet::SINK_INPUT | InterestMaskSet::SERVER)?;
+    let mut stdout = io::stdout().lock();
+    let mut info = info;
+    let mut previous = None;
+    loop {
+        let line = match status_line(&info, target, format, icons, scale) {
+            Err(CliError::NotFound(_)) => String::new(),
+            line => line?,
+        };
+        if previous.as_ref() != Some(&line) {
+            writeln!(stdout, "{line}")
+                .and_then(|_| stdout.flush())
+                .map_err(|e| CliError::Output(e.to_string()))?;
+            previous = Some(line);
+        }
+        info = match api.wait_for_changes().and_then(|_| api.get_volume_info()) {
+            Ok(info) => info,
+            Err(e) => {
+                // Don't leave a status bar showing a volume that may be out of date
+                let _ = writeln!(stdout).and_then(|_| stdout.flush());
+                return Err(e.into());
+            }
+        };
+    }
+}
+
+/// Report changes as they are announced by the server. Only returns on error, including when
+/// whoever is reading our output goes away.
+fn watch(
+    api: &mut PulseAPI,
+    mut previous: VolumeInfo,
+    target: Option<&Target>,
+    scale: Scale,
+) -> Result<(), CliError> {
+    api.subscribe(InterestMaskSet::SINK | InterestMaskSet::SINK_INPUT | InterestMaskSet::SERVER)?;
+    let mut stdout = io::stdout().lock();
+    loop {
+        api.wait_for_changes()?;
+        let current = api.get_volume_info()?;
+        for event in ChangeEvent::diff(&previous, &current, scale) {
+            if target.is_some_and(|t| !event.concerns(t, &previous, &current)) {
+                continue;
+            }
+            let line =
+                serde_json::to_string(&event).map_err(|e| CliError::Output(e.to_string()))?;
+            writeln!(stdout, "{line}")
+                .and_then(|_| stdout.flush())
+                .map_err(|e| CliError::Output(e.to_string()))?;
+        }
+        previous = current;
+    }
+}
+
+fn scene(api: &mut PulseAPI, command: SceneCommand) -> Result<(), CliError> {
+    match command {
+        SceneCommand::List => Scene::names()?.iter().for_each(|name| println!("{name}")),
+        SceneCommand::Save { name } => {
+            let info = api.get_volume_info()?;
+            let server = api.get_server_information()?;
+            let path = Scene::capture(&info, &server).save(&name)?;
+            println!("Saved {}", path.display());
+        }
+        SceneCommand::Apply { name } => Scene::load(&name)?.apply(api)?,
+        SceneCommand::Delete { name } => Scene::delete(&name)?,
+    }
+    Ok(())
+}
+
+fn virtual_devices(api: &mut PulseAPI, command: VirtualCommand) -> Result<(), CliError> {
+    let device = match command {
+        VirtualCommand::List => {
+            for device in virtual_device::list(api)? {
+                println!("{device}");
+            }
+            return Ok(());
+        }
+        VirtualCommand::Remove { device, all: _ } => {
+            for device in virtual_device::remove(api, device.as_ref())? {
+                println!("Removed {device}");
+            }
+            return Ok(());
+        }
+        VirtualCommand::NullSink { name, description } => {
+            VirtualDevice::NullSink { name, description }
+        }
+        VirtualCommand::CombineSink {
+            name,
+            sinks,
+            description,
+        } => VirtualDevice::CombineSink {
+            name,
+            description,
+            sinks,
+        },
+        VirtualCommand::Loopback {
+            source,
+            sink,
+            latency_msec,
+        } => VirtualDevice::Loopback {
+            source,
+            sink,
+            latency_msec,
+        },
+        VirtualCommand::RemapSink {
+            name,
+            master,
+            channel_map,
+            description,
+        } => VirtualDevice::RemapSink {
+            name,
+            description,
+            master,
+            channel_map,
+        },
+    };
+    let module = virtual_device::create(api, &device)?;
+    println!("Created {} as module {module}", device.kind());
+    Ok(())
+}
+
+/// Apply the rules to streams as they appear. Only returns on error.
+fn run_rules(
+    api: &mut PulseAPI,
+    info: VolumeInfo,
+    rules: &[Rule],
+    scale: Scale,
+    existing: bool,
+) -> Result<(), CliError> {
+    if rules.is_empty() {
+        log::warn!("No rules in the config file, there is nothing to do");
+    }
+    api.subscribe(InterestMaskSet::SINK_INPUT)?;
+    if existing {
+        rules::apply_matching(api, &info, info.sink_inputs(), rules, scale);
+    }
+
+    loop {
+        let new: Vec<u32> = api
+            .wait_for_changes()?
+            .into_iter()
+            .filter(|n| {
+                n.facility == Facility::SinkInput && n.operation == SubscriptionOperation::New
+            })
+            .map(|n| n.index)
+            .collect();
+        if new.is_empty() {
+            continue;
+        }
+        let info = api.get_volume_info()?;
+        let inputs = new.iter().filter_map(|index| info.sink_input(*index));
+        rules::apply_matching(api, &info, inputs, rules, scale);
+    }
+}
+
+fn dry_run_rules(info: &VolumeInfo, rules: &[Rule]) {
+    let mut inputs: Vec<_> = info.sink_inputs().collect();
+    inputs.sort_by_key(|i| i.index);
+    for input in inputs {
+        let app = input
+            .proplist
+            .get_str(pulse::proplist::properties::APPLICATION_NAME)
+            .unwrap_or_default();
+        println!(
+            "input {} {}: {}",
+            input.index,
+            app,
+            input.name.as_deref().unwrap_or_default()
+        );
+        let mut matched = false;
+        for (i, rule) in rules::matching(rules, input) {
+            println!("  rule {} ({rule}): {}", i + 1, rule.actions());
+            matched = true;
+        }
+        if !matched {
+            println!("  no matching rule");
+        }
+    }
+}
+
+fn print_volume(percent: f64, mute: bool) {
+    match mute {
+        true => println!("{percent:.0}% muted"),
+        false => println!("{percent:.0}%"),
+    }
+}
+
+/// Print a listing in the `list` schema, whether it was made here or by the daemon
+fn list(listing: &Value, format: OutputFormat, scale: Scale) -> Result<(), CliError> {
+    match format {
+        OutputFormat::Table => {
+            let listing =
+                Listing::deserialize(listing).map_err(|e| CliError::Output(e.to_string()))?;
+            print_table(&listing, scale)
+        }
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(listing).map_err(|e| CliError::Output(e.to_string()))?
+        ),
+        OutputFormat::Yaml => print!(
+            "{}",
+            serde_yaml::to_string(listing).map_err(|e| CliError::Output(e.to_string()))?
+        ),
+    }
+    Ok(())
+}
+
+/// The parts of the `list` schema the table shows
+#[derive(Deserialize)]
+struct Listing {
+    default_sink_name: Option<String>,
+    sinks: Vec<ListedSink>,
+}
+
+#[derive(Deserialize)]
+struct ListedSink {
+    sink: Listed,
+    sink_inputs: Vec<Listed>,
+}
+
+/// A sink or sink input
+#[derive(Deserialize)]
+struct Listed {
+    index: u32,
+    name: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    volume: ListedVolume,
+    mute: bool,
+    proplist: BTreeMap<String, Option<String>>,
+}
+
+#[derive(Deserialize)]
+struct ListedVolume {
+    raw: Vec<u32>,
+}
+
+impl ListedVolume {
+    /// The average of the channels, as [`ChannelVolumes::avg`](pulse::volume::ChannelVolumes)
+    /// works it out
+    fn avg(&self) -> Volume {
+        let sum: u64 = self.raw.iter().map(|&v| u64::from(v)).sum();
+        Volume((sum / self.raw.len().max(1) as u64) as u32)
+    }
+}
+
+fn print_table(listing: &Listing, scale: Scale) {
+    for entry in &listing.sinks {
+        let sink = &entry.sink;
+        let default = listing.default_sink_name.is_some() && sink.name == listing.default_sink_name;
+        println!(
+            "sink {} {:>4.0}%{} {} ({}){}",
+            sink.index,
+            scale.to_percent(sink.volume.avg()),
+            if sink.mute { " muted" } else { "" },
+            sink.name.as_deref().unwrap_or_default(),
+            sink.description.as_deref().unwrap_or_default(),
+            if default { " [default]" } else { "" },
+        );
+
+        let mut inputs: Vec<_> = entry.sink_inputs.iter().collect();
+        inputs.sort_by_key(|i| i.index);
+        for input in inputs {
+            let app = input
+                .proplist
+                .get(pulse::proplist::properties::APPLICATION_NAME)
+                .and_then(Option::as_deref)
+                .unwrap_or_default();
+            println!(
+                "  input {} {:>4.0}%{} {}: {}",
+                input.index,
+                scale.to_percent(input.volume.avg()),
+                if input.mute { " muted" } else { "" },
+                app,
+                input.name.as_deref().unwrap_or_default(),
+            );
+        }
+    }
+}
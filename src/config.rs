@@ -0,0 +1,317 @@
+//! User configuration, read from `$XDG_CONFIG_HOME/tmix/config.toml`
+//!
+//! Every setting is optional. A complete file with the default values looks like:
+//!
+//! ```toml
+//! [general]
+//! title = "TMIX"
+//! volume_step = 5          # percent per key press
+//! max_volume = 100         # percent volumes can be raised to, up to 150 to amplify
+//! scale = "linear"         # or "cubic", PulseAudio's own percentages
+//! default_view = "playback" # "recording", "output_devices", "input_devices",
+//!                           # "configuration", "latency", "modules", "patchbay",
+//!                           # "applications" or "scenes"
+//! layout = "vertical"      # or "horizontal", one row per meter
+//! columns = 5              # meters shown side by side in the vertical layout
+//! poll_interval_ms = 10
+//!
+//! [server]
+//! # address = "unix:/run/user/1000/pulse/native"
+//! connect_timeout_ms = 5000    # how long the server gets to accept the connection
+//! operation_timeout_ms = 5000  # and to answer each request
+//!
+//! [daemon]
+//! # socket = "/run/user/1000/tmix.sock"   # where `tmix daemon` listens
+//!
+//! [theme]
+//! name = "dark"            # "light", "high_contrast" or "monochrome"
+//! color_depth = "auto"     # "16", "256" or "truecolor"
+//! # Any colour of the theme can be replaced with a colour name, "#rrggbb" or a 256 colour index
+//! # background, text, border, focused_border, track, ticks, thumb, muted, boost, peak
+//! # track = "darkgray"
+//!
+//! [keybindings]
+//! quit = ["q", "esc"]
+//! next_view = ["tab"]
+//! previous_view = ["backtab"]
+//! show_playback = ["1"]
+//! show_recording = ["2"]
+//! show_output_devices = ["3"]
+//! show_input_devices = ["4"]
+//! show_configuration = ["5"]
+//! show_latency = ["6"]
+//! show_modules = ["7"]
+//! show_patchbay = ["8"]
+//! show_applications = ["9"]
+//! show_scenes = ["0"]
+//! select_previous = ["left", "h"]
+//! select_next = ["right", "l"]
+//! volume_up = ["up", "k"]
+//! volume_down = ["down", "j"]
+//! toggle_mute = ["m"]
+//! cycle_theme = ["t"]
+//! toggle_layout = ["v"]
+//! expand = ["+"]
+//! collapse = ["-"]
+//! toggle_expand = ["space"]
+//! activate = ["enter"]
+//! help = ["?"]
+//! details = ["i"]
+//! kill = ["x"]
+//! load_module = ["a"]
+//! new_virtual_device = ["c"]
+//! command_palette = [":"]
+//! search = ["/"]
+//! search_next = ["n"]
+//! search_previous = ["N"]
+//!
+//! # Settings applied to new streams by `tmix rules run`, see the rules module
+//! # [[rules]]
+//! # application = "Spotify"
+//! # volume = 40
+//! ```
+mod keys;
+mod theme;
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+use crate::{
+    data::{Scale, MAX_PERCENT},
+    pulse_api::Timeouts,
+    rules::Rule,
+};
+
+pub use keys::{Action, Key, Keybindings};
+pub use theme::{parse_color, ColorDepth, Theme, ThemeConfig, ThemeName};
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("could not read {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("invalid config file {path}:\n{source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub general: General,
+    pub server: Server,
+    pub daemon: Daemon,
+    pub theme: ThemeConfig,
+    pub keybindings: Keybindings,
+    pub rules: Vec<Rule>,
+}
+
+impl Config {
+    /// Directory holding TMIX's config file and saved scenes
+    pub fn dir() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .map(|dir| dir.join("tmix"))
+    }
+
+    /// Where the config file is looked for
+    pub fn default_path() -> Option<PathBuf> {
+        Self::dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// Load the config from `path`, or from the default location when no path is given.
+    ///
+    /// A missing file at the default location is not an error, it just means every setting
+    /// keeps its default.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+
+        let text = fs::read_to_string(&path).map_err(|source| ConfigError::Read {
+            path: path.clone(),
+            source,
+        })?;
+        toml::from_str(&text).map_err(|source| ConfigError::Parse { path, source })
+    }
+}
+
+/// Which screen the mixer opens on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultView {
+    #[default]
+    Playback,
+    Recording,
+    #[serde(alias = "sinks")]
+    OutputDevices,
+    InputDevices,
+    Configuration,
+    Latency,
+    Modules,
+    Patchbay,
+    Applications,
+    Scenes,
+}
+
+/// How the mixer draws its meters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeterLayout {
+    /// Vertical meters side by side, in columns
+    #[default]
+    Vertical,
+    /// One labelled row per meter, which fits many more on screen
+    Horizontal,
+}
+
+impl MeterLayout {
+    pub fn toggle(self) -> Self {
+        match self {
+            MeterLayout::Vertical => MeterLayout::Horizontal,
+            MeterLayout::Horizontal => MeterLayout::Vertical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct General {
+    /// Title shown on the mixer's border
+    pub title: String,
+    /// Percent the volume changes per key press
+    #[serde(deserialize_with = "volume_step")]
+    pub volume_step: f64,
+    /// Percent the mixer and `tmix set-volume` raise volumes to at most
+    #[serde(deserialize_with = "max_volume")]
+    pub max_volume: f64,
+    pub scale: Scale,
+    pub default_view: DefaultView,
+    pub layout: MeterLayout,
+    /// Number of meters shown side by side in the vertical layout
+    #[serde(deserialize_with = "columns")]
+    pub columns: usize,
+    /// How long to wait for input before refreshing the mixer
+    #[serde(rename = "poll_interval_ms", deserialize_with = "millis")]
+    pub poll_interval: Duration,
+}
+
+impl Default for General {
+    fn default() -> Self {
+        Self {
+            title: "TMIX".to_string(),
+            volume_step: 5.0,
+            max_volume: 100.0,
+            scale: Scale::default(),
+            default_view: DefaultView::default(),
+            layout: MeterLayout::default(),
+            columns: 5,
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Server {
+    /// Address of the PulseAudio server, the default server when unset
+    pub address: Option<String>,
+    /// How long the server gets to accept the connection
+    #[serde(rename = "connect_timeout_ms", deserialize_with = "timeout")]
+    pub connect_timeout: Duration,
+    /// How long the server gets to answer each request
+    #[serde(rename = "operation_timeout_ms", deserialize_with = "timeout")]
+    pub operation_timeout: Duration,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        let timeouts = Timeouts::default();
+        Self {
+            address: None,
+            connect_timeout: timeouts.connect,
+            operation_timeout: timeouts.operation,
+        }
+    }
+}
+
+impl Server {
+    pub fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            connect: self.connect_timeout,
+            operation: self.operation_timeout,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Daemon {
+    /// Socket the daemon listens on, `$XDG_RUNTIME_DIR/tmix.sock` when unset. Its directory
+    /// must belong to the user and not be writable by anyone else.
+    pub socket: Option<PathBuf>,
+}
+
+impl Daemon {
+    pub fn socket_path(&self) -> PathBuf {
+        self.socket
+            .clone()
+            .unwrap_or_else(crate::daemon::default_socket_path)
+    }
+}
+
+fn volume_step<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let step = f64::deserialize(deserializer)?;
+    match step > 0.0 && step <= 100.0 {
+        true => Ok(step),
+        false => Err(serde::de::Error::custom(format!(
+            "volume_step must be more than 0 and at most 100, not {step}"
+        ))),
+    }
+}
+
+fn max_volume<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let max = f64::deserialize(deserializer)?;
+    match (100.0..=MAX_PERCENT).contains(&max) {
+        true => Ok(max),
+        false => Err(serde::de::Error::custom(format!(
+            "max_volume must be between 100 and {MAX_PERCENT}, not {max}"
+        ))),
+    }
+}
+
+fn columns<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let columns = usize::deserialize(deserializer)?;
+    match (1..=32).contains(&columns) {
+        true => Ok(columns),
+        false => Err(serde::de::Error::custom(format!(
+            "columns must be between 1 and 32, not {columns}"
+        ))),
+    }
+}
+
+fn millis<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_millis)
+}
+
+fn timeout<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    match millis(deserializer)? {
+        timeout if timeout.is_zero() => Err(serde::de::Error::custom(
+            "timeouts must be at least 1 millisecond",
+        )),
+        timeout => Ok(timeout),
+    }
+}
@@ -0,0 +1,434 @@
+//! Keys and the actions they are bound to
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Something the user can do from the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    NextView,
+    PreviousView,
+    ShowPlayback,
+    ShowRecording,
+    ShowOutputDevices,
+    ShowInputDevices,
+    ShowConfiguration,
+    ShowLatency,
+    ShowModules,
+    ShowPatchbay,
+    ShowApplications,
+    ShowScenes,
+    SelectPrevious,
+    SelectNext,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    CycleTheme,
+    ToggleLayout,
+    Expand,
+    Collapse,
+    ToggleExpand,
+    Activate,
+    Help,
+    Details,
+    Kill,
+    LoadModule,
+    NewVirtualDevice,
+    CommandPalette,
+    Search,
+    SearchNext,
+    SearchPrevious,
+}
+
+impl Action {
+    /// Name of the action as written in the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::NextView => "next_view",
+            Action::PreviousView => "previous_view",
+            Action::ShowPlayback => "show_playback",
+            Action::ShowRecording => "show_recording",
+            Action::ShowOutputDevices => "show_output_devices",
+            Action::ShowInputDevices => "show_input_devices",
+            Action::ShowConfiguration => "show_configuration",
+            Action::ShowLatency => "show_latency",
+            Action::ShowModules => "show_modules",
+            Action::ShowPatchbay => "show_patchbay",
+            Action::ShowApplications => "show_applications",
+            Action::ShowScenes => "show_scenes",
+            Action::SelectPrevious => "select_previous",
+            Action::SelectNext => "select_next",
+            Action::VolumeUp => "volume_up",
+            Action::VolumeDown => "volume_down",
+            Action::ToggleMute => "toggle_mute",
+            Action::CycleTheme => "cycle_theme",
+            Action::ToggleLayout => "toggle_layout",
+            Action::Expand => "expand",
+            Action::Collapse => "collapse",
+            Action::ToggleExpand => "toggle_expand",
+            Action::Activate => "activate",
+            Action::Help => "help",
+            Action::Details => "details",
+            Action::Kill => "kill",
+            Action::LoadModule => "load_module",
+            Action::NewVirtualDevice => "new_virtual_device",
+            Action::CommandPalette => "command_palette",
+            Action::Search => "search",
+            Action::SearchNext => "search_next",
+            Action::SearchPrevious => "search_previous",
+        }
+    }
+
+    /// Short description of what the action does
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::NextView => "Switch to the next view",
+            Action::PreviousView => "Switch to the previous view",
+            Action::ShowPlayback => "Show the playback streams",
+            Action::ShowRecording => "Show the recording streams",
+            Action::ShowOutputDevices => "Show the output devices",
+            Action::ShowInputDevices => "Show the input devices",
+            Action::ShowConfiguration => "Show the card profiles",
+            Action::ShowLatency => "Show the latency of the sinks and their streams",
+            Action::ShowModules => "Show the loaded modules",
+            Action::ShowPatchbay => "Show how streams and devices are connected",
+            Action::ShowApplications => "Show the volume of each application",
+            Action::ShowScenes => "Show the saved scenes",
+            Action::SelectPrevious => "Select the previous item",
+            Action::SelectNext => "Select the next item",
+            Action::VolumeUp => "Raise the volume",
+            Action::VolumeDown => "Lower the volume",
+            Action::ToggleMute => "Mute or unmute",
+            Action::CycleTheme => "Switch to the next theme",
+            Action::ToggleLayout => "Switch between vertical and horizontal meters",
+            Action::Expand => "Show the streams of the selected device",
+            Action::Collapse => "Hide the streams of the selected device",
+            Action::ToggleExpand => "Show or hide the streams of the selected device",
+            Action::Activate => {
+                "Make the selected device the default, apply the scene, or move a stream"
+            }
+            Action::Help => "Show the keybindings",
+            Action::Details => "Show every property of the selected item",
+            Action::Kill => "Disconnect the selected stream or application, or unload the module",
+            Action::LoadModule => "Load a module",
+            Action::NewVirtualDevice => "Create a virtual sink or loopback",
+            Action::CommandPalette => "Open the command palette",
+            Action::Search => "Filter by name or property",
+            Action::SearchNext => "Select the next match",
+            Action::SearchPrevious => "Select the previous match",
+        }
+    }
+}
+
+/// A key press, written like `q`, `space`, `ctrl+c` or `shift+tab`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    const fn plain(code: KeyCode) -> Self {
+        Self::new(code, KeyModifiers::NONE)
+    }
+
+    const fn char(c: char) -> Self {
+        Self::plain(KeyCode::Char(c))
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        // Shift is already part of upper case and symbol characters, and of backtab
+        let modifiers = match event.code {
+            KeyCode::Char(_) | KeyCode::BackTab => event.modifiers - KeyModifiers::SHIFT,
+            _ => event.modifiers,
+        };
+        Self::new(event.code, modifiers)
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').collect();
+        // A binding for the '+' key itself ends with an empty part
+        let name = match parts.pop() {
+            Some("") if parts.last() == Some(&"") => {
+                parts.pop();
+                "+"
+            }
+            Some(name) => name,
+            None => unreachable!("split always yields at least one part"),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{modifier}' in key '{s}'")),
+            };
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match name.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                f if f.starts_with('f') && f[1..].parse::<u8>().is_ok() => {
+                    KeyCode::F(f[1..].parse().expect("checked above"))
+                }
+                _ => return Err(format!("unknown key '{name}'")),
+            },
+        };
+
+        // Match how terminals report shifted characters, as the upper case character
+        if let KeyCode::Char(c) = code {
+            if modifiers.contains(KeyModifiers::SHIFT) {
+                let upper = c.to_uppercase().next().unwrap_or(c);
+                return Ok(Key::new(
+                    KeyCode::Char(upper),
+                    modifiers - KeyModifiers::SHIFT,
+                ));
+            }
+        }
+        Ok(Key::new(code, modifiers))
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "backtab"),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Insert => write!(f, "insert"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+/// Which keys trigger which actions.
+///
+/// In the config file this is a table of action names to lists of keys. Actions that are left
+/// out keep their default keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keybindings {
+    bindings: BTreeMap<Action, Vec<Key>>,
+}
+
+impl Keybindings {
+    /// The action bound to a key press, if any
+    pub fn action(&self, key: impl Into<Key>) -> Option<Action> {
+        let key = key.into();
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    /// Every action along with the keys bound to it
+    pub fn iter(&self) -> impl Iterator<Item = (Action, &[Key])> {
+        self.bindings.iter().map(|(a, k)| (*a, k.as_slice()))
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        let bindings = BTreeMap::from([
+            (Action::Quit, vec![Key::char('q'), Key::plain(KeyCode::Esc)]),
+            (Action::NextView, vec![Key::plain(KeyCode::Tab)]),
+            (Action::PreviousView, vec![Key::plain(KeyCode::BackTab)]),
+            (Action::ShowPlayback, vec![Key::char('1')]),
+            (Action::ShowRecording, vec![Key::char('2')]),
+            (Action::ShowOutputDevices, vec![Key::char('3')]),
+            (Action::ShowInputDevices, vec![Key::char('4')]),
+            (Action::ShowConfiguration, vec![Key::char('5')]),
+            (Action::ShowLatency, vec![Key::char('6')]),
+            (Action::ShowModules, vec![Key::char('7')]),
+            (Action::ShowPatchbay, vec![Key::char('8')]),
+            (Action::ShowApplications, vec![Key::char('9')]),
+            (Action::ShowScenes, vec![Key::char('0')]),
+            (
+                Action::SelectPrevious,
+                vec![Key::plain(KeyCode::Left), Key::char('h')],
+            ),
+            (
+                Action::SelectNext,
+                vec![Key::plain(KeyCode::Right), Key::char('l')],
+            ),
+            (
+                Action::VolumeUp,
+                vec![Key::plain(KeyCode::Up), Key::char('k')],
+            ),
+            (
+                Action::VolumeDown,
+                vec![Key::plain(KeyCode::Down), Key::char('j')],
+            ),
+            (Action::ToggleMute, vec![Key::char('m')]),
+            (Action::CycleTheme, vec![Key::char('t')]),
+            (Action::ToggleLayout, vec![Key::char('v')]),
+            (Action::Expand, vec![Key::char('+')]),
+            (Action::Collapse, vec![Key::char('-')]),
+            (Action::ToggleExpand, vec![Key::char(' ')]),
+            (Action::Activate, vec![Key::plain(KeyCode::Enter)]),
+            (Action::Help, vec![Key::char('?')]),
+            (Action::Details, vec![Key::char('i')]),
+            (Action::Kill, vec![Key::char('x')]),
+            (Action::LoadModule, vec![Key::char('a')]),
+            (Action::NewVirtualDevice, vec![Key::char('c')]),
+            (Action::CommandPalette, vec![Key::char(':')]),
+            (Action::Search, vec![Key::char('/')]),
+            (Action::SearchNext, vec![Key::char('n')]),
+            (Action::SearchPrevious, vec![Key::char('N')]),
+        ]);
+        Self { bindings }
+    }
+}
+
+impl<'de> Deserialize<'de> for Keybindings {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let overrides = BTreeMap::<Action, Vec<Key>>::deserialize(deserializer)?;
+        let mut keybindings = Keybindings::default();
+        keybindings.bindings.extend(overrides);
+
+        let mut seen: BTreeMap<String, Action> = BTreeMap::new();
+        for (action, keys) in keybindings.iter() {
+            for key in keys {
+                if let Some(other) = seen.insert(key.to_string(), action) {
+                    return Err(serde::de::Error::custom(format!(
+                        "key '{key}' is bound to both {} and {}",
+                        other.name(),
+                        action.name()
+                    )));
+                }
+            }
+        }
+        Ok(keybindings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!("q".parse(), Ok(Key::char('q')));
+        assert_eq!("space".parse(), Ok(Key::char(' ')));
+        assert_eq!("PageDown".parse(), Ok(Key::plain(KeyCode::PageDown)));
+        assert_eq!("f5".parse(), Ok(Key::plain(KeyCode::F(5))));
+        assert_eq!(
+            "ctrl+c".parse(),
+            Ok(Key::new(KeyCode::Char('c'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            "ctrl++".parse(),
+            Ok(Key::new(KeyCode::Char('+'), KeyModifiers::CONTROL))
+        );
+        assert_eq!("+".parse(), Ok(Key::char('+')));
+    }
+
+    #[test]
+    fn shifted_characters_are_upper_case() {
+        assert_eq!("shift+n".parse(), Ok(Key::char('N')));
+        let event = KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT);
+        assert_eq!(Key::from(event), Key::char('N'));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert_eq!(
+            "hyper+x".parse::<Key>(),
+            Err("unknown modifier 'hyper' in key 'hyper+x'".into())
+        );
+        assert_eq!("fn".parse::<Key>(), Err("unknown key 'fn'".into()));
+    }
+
+    #[test]
+    fn keys_round_trip_through_display() {
+        for text in ["q", "space", "ctrl+alt+delete", "backtab", "f12", "+"] {
+            assert_eq!(text.parse::<Key>().unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn overrides_replace_an_actions_default_keys() {
+        let keybindings: Keybindings =
+            toml::from_str("quit = [\"ctrl+q\"]\nnext_view = [\"q\"]").unwrap();
+        assert_eq!(
+            keybindings.action(Key::new(KeyCode::Char('q'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(keybindings.action(Key::char('q')), Some(Action::NextView));
+        assert_eq!(keybindings.action(Key::plain(KeyCode::Esc)), None);
+        assert_eq!(keybindings.action(Key::char('m')), Some(Action::ToggleMute));
+    }
+
+    #[test]
+    fn rejects_keys_bound_twice() {
+        let error = toml::from_str::<Keybindings>("next_view = [\"q\"]").unwrap_err();
+        assert_eq!(
+            error.message(),
+            "key 'q' is bound to both quit and next_view"
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! Colours used to draw the mixer
+use std::{env, str::FromStr};
+
+use serde::{Deserialize, Deserializer};
+use tui::style::Color;
+
+/// The colours of every part of the mixer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Fill behind everything
+    pub background: Color,
+    /// Labels such as the volume percentage
+    pub text: Color,
+    /// Borders of meters that aren't selected
+    pub border: Color,
+    /// Border of the selected meter
+    pub focused_border: Color,
+    /// The meter's vertical track
+    pub track: Color,
+    /// Tick marks along the track
+    pub ticks: Color,
+    /// The marker showing the current volume
+    pub thumb: Color,
+    /// Thumb and label of a muted meter
+    pub muted: Color,
+    /// The part of the track above 100%
+    pub boost: Color,
+    /// Thumb of a meter whose volume is above 100%
+    pub peak: Color,
+}
+
+/// The themes that ship with TMIX
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+    Monochrome,
+}
+
+impl ThemeName {
+    pub const ALL: [ThemeName; 4] = [
+        ThemeName::Dark,
+        ThemeName::Light,
+        ThemeName::HighContrast,
+        ThemeName::Monochrome,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThemeName::Dark => "dark",
+            ThemeName::Light => "light",
+            ThemeName::HighContrast => "high_contrast",
+            ThemeName::Monochrome => "monochrome",
+        }
+    }
+
+    /// The built-in theme after this one, wrapping around
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn theme(&self) -> Theme {
+        match self {
+            ThemeName::Dark => Theme {
+                background: Color::Reset,
+                text: Color::Reset,
+                border: Color::Gray,
+                focused_border: Color::White,
+                track: Color::DarkGray,
+                ticks: Color::DarkGray,
+                thumb: Color::Gray,
+                muted: Color::Red,
+                boost: Color::Yellow,
+                peak: Color::LightRed,
+            },
+            ThemeName::Light => Theme {
+                background: Color::White,
+                text: Color::Black,
+                border: Color::DarkGray,
+                focused_border: Color::Blue,
+                track: Color::Gray,
+                ticks: Color::DarkGray,
+                thumb: Color::Black,
+                muted: Color::Red,
+                boost: Color::Magenta,
+                peak: Color::Red,
+            },
+            ThemeName::HighContrast => Theme {
+                background: Color::Black,
+                text: Color::White,
+                border: Color::White,
+                focused_border: Color::LightYellow,
+                track: Color::White,
+                ticks: Color::White,
+                thumb: Color::LightYellow,
+                muted: Color::LightRed,
+                boost: Color::LightMagenta,
+                peak: Color::LightRed,
+            },
+            ThemeName::Monochrome => Theme {
+                background: Color::Reset,
+                text: Color::Reset,
+                border: Color::Reset,
+                focused_border: Color::Reset,
+                track: Color::Reset,
+                ticks: Color::Reset,
+                thumb: Color::Reset,
+                muted: Color::Reset,
+                boost: Color::Reset,
+                peak: Color::Reset,
+            },
+        }
+    }
+}
+
+/// How many colours the terminal can show
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorDepth {
+    /// Guess from the `COLORTERM` and `TERM` environment variables
+    #[default]
+    Auto,
+    #[serde(rename = "16")]
+    Ansi16,
+    #[serde(rename = "256")]
+    Ansi256,
+    #[serde(rename = "truecolor")]
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Work out what `Auto` means for this terminal
+    pub fn detect(self) -> Self {
+        if self != ColorDepth::Auto {
+            return self;
+        }
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    /// Replace a colour the terminal can't show with the closest one it can
+    pub fn adapt(self, color: Color) -> Color {
+        match (self.detect(), color) {
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(rgb_to_256(r, g, b)),
+            (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => rgb_to_16(r, g, b),
+            (ColorDepth::Ansi16, Color::Indexed(i)) => {
+                let (r, g, b) = indexed_to_rgb(i);
+                rgb_to_16(r, g, b)
+            }
+            (_, color) => color,
+        }
+    }
+}
+
+/// Channel levels of the 6x6x6 colour cube in the 256 colour palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn nearest_level(v: u8) -> usize {
+    CUBE_LEVELS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, level)| (**level as i16 - v as i16).abs())
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    // Greys get the finer grey ramp rather than the six cube greys
+    if r == g && g == b {
+        return match r {
+            0..=7 => 16,
+            248..=255 => 231,
+            _ => 232 + ((r as u16 - 8) * 24 / 240) as u8,
+        };
+    }
+    16 + 36 * nearest_level(r) as u8 + 6 * nearest_level(g) as u8 + nearest_level(b) as u8
+}
+
+fn indexed_to_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => BASIC_COLORS[i as usize].1,
+        16..=231 => {
+            let i = i - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[((i / 6) % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let grey = 8 + (i - 232) * 10;
+            (grey, grey, grey)
+        }
+    }
+}
+
+/// The 16 basic colours, with the usual xterm values
+const BASIC_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn rgb_to_16(r: u8, g: u8, b: u8) -> Color {
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, cr) + d(g, cg) + d(b, cb)
+    };
+    BASIC_COLORS
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
+
+/// The `[theme]` section: a built-in theme, with any of its colours replaced
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub name: ThemeName,
+    pub color_depth: ColorDepth,
+    #[serde(deserialize_with = "color")]
+    pub background: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub text: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub border: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub focused_border: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub track: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub ticks: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub thumb: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub muted: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub boost: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub peak: Option<Color>,
+}
+
+impl ThemeConfig {
+    /// The colours to draw with for a built-in theme. The colours set in the config only
+    /// replace those of the configured theme, so other themes can still be switched to as
+    /// they are.
+    pub fn theme(&self, name: ThemeName) -> Theme {
+        let mut theme = name.theme();
+        if name == self.name {
+            let overrides = [
+                (&mut theme.background, self.background),
+                (&mut theme.text, self.text),
+                (&mut theme.border, self.border),
+                (&mut theme.focused_border, self.focused_border),
+                (&mut theme.track, self.track),
+                (&mut theme.ticks, self.ticks),
+                (&mut theme.thumb, self.thumb),
+                (&mut theme.muted, self.muted),
+                (&mut theme.boost, self.boost),
+                (&mut theme.peak, self.peak),
+            ];
+            for (slot, color) in overrides {
+                if let Some(color) = color {
+                    *slot = color;
+                }
+            }
+        }
+
+        let depth = self.color_depth.detect();
+        for slot in [
+            &mut theme.background,
+            &mut theme.text,
+            &mut theme.border,
+            &mut theme.focused_border,
+            &mut theme.track,
+            &mut theme.ticks,
+            &mut theme.thumb,
+            &mut theme.muted,
+            &mut theme.boost,
+            &mut theme.peak,
+        ] {
+            *slot = depth.adapt(*slot);
+        }
+        theme
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Color>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    parse_color(&name)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Parse a colour name such as `darkgray`, a `#rrggbb` hex colour or a 256 colour index
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    if let Some(hex) = s.strip_prefix('#') {
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(format!("'{s}' is not a #rrggbb colour")),
+        };
+    }
+    if let Ok(index) = u8::from_str(s) {
+        return Ok(Color::Indexed(index));
+    }
+
+    let color = match s.to_lowercase().replace(['_', '-', ' '], "").as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return Err(format!("unknown colour '{s}'")),
+    };
+    Ok(color)
+}
@@ -0,0 +1,291 @@
+//! Changes to sinks and streams named by a [`Target`], shared by the command line and the daemon
+use std::{fmt, io, str::FromStr};
+
+use clap::ValueEnum;
+use pulse::volume::ChannelVolumes;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{
+    data::{limit_percent, Scale, SinkInformation, SinkInputInformation},
+    pulse_api::{PulseAPI, VolumeInfo},
+    target::{KillTarget, Selector, Target, TargetKind},
+};
+
+#[derive(Debug, Error)]
+pub enum ControlError {
+    #[error("no match for {0}")]
+    NotFound(String),
+    #[error("'{0}' does not refer to a stream")]
+    NotAStream(String),
+    #[error(transparent)]
+    Pulse(#[from] io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MuteAction {
+    Toggle,
+    On,
+    Off,
+}
+
+impl MuteAction {
+    pub fn apply(self, muted: bool) -> bool {
+        match self {
+            MuteAction::Toggle => !muted,
+            MuteAction::On => true,
+            MuteAction::Off => false,
+        }
+    }
+}
+
+/// A new volume in percent, either absolute (`50`) or relative (`+5`, `-10%`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolumeChange {
+    Set(f64),
+    Adjust(f64),
+}
+
+impl VolumeChange {
+    pub fn apply(self, percent: f64) -> f64 {
+        match self {
+            VolumeChange::Set(p) => p,
+            VolumeChange::Adjust(delta) => percent + delta,
+        }
+    }
+}
+
+impl FromStr for VolumeChange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s.trim_end_matches('%');
+        let value = number
+            .parse()
+            .ok()
+            .filter(|value: &f64| value.is_finite())
+            .ok_or_else(|| format!("'{s}' is not a volume percentage"))?;
+        match number.starts_with(['+', '-']) {
+            true => Ok(VolumeChange::Adjust(value)),
+            false => Ok(VolumeChange::Set(value)),
+        }
+    }
+}
+
+impl fmt::Display for VolumeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VolumeChange::Set(p) => write!(f, "{p}"),
+            VolumeChange::Adjust(delta) => write!(f, "{delta:+}"),
+        }
+    }
+}
+
+impl Serialize for VolumeChange {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for VolumeChange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// The objects a target resolved to
+pub enum Resolved<'a> {
+    Sink(&'a SinkInformation),
+    SinkInputs(Vec<&'a SinkInputInformation>),
+}
+
+pub fn resolve<'a>(info: &'a VolumeInfo, target: &Target) -> Result<Resolved<'a>, ControlError> {
+    let not_found = || ControlError::NotFound(target.to_string());
+    match target.kind {
+        TargetKind::Sink => info
+            .find_sink(&target.selector)
+            .map(Resolved::Sink)
+            .ok_or_else(not_found),
+        TargetKind::SinkInput => match info.find_sink_inputs(&target.selector) {
+            inputs if inputs.is_empty() => Err(not_found()),
+            inputs => Ok(Resolved::SinkInputs(inputs)),
+        },
+    }
+}
+
+pub fn find_sink<'a>(
+    info: &'a VolumeInfo,
+    sink: &Selector,
+) -> Result<&'a SinkInformation, ControlError> {
+    info.find_sink(sink)
+        .ok_or_else(|| ControlError::NotFound(format!("sink:{sink}")))
+}
+
+/// The volume in percent and mute state of everything the target resolves to
+pub fn volumes(
+    info: &VolumeInfo,
+    target: &Target,
+    scale: Scale,
+) -> Result<Vec<(f64, bool)>, ControlError> {
+    Ok(match resolve(info, target)? {
+        Resolved::Sink(sink) => vec![(scale.to_percent(sink.volume.avg()), sink.mute)],
+        Resolved::SinkInputs(inputs) => inputs
+            .iter()
+            .map(|i| (scale.to_percent(i.volume.avg()), i.mute))
+            .collect(),
+    })
+}
+
+/// Change the volume of a target, raising it to at most `max` percent
+pub fn set_volume(
+    api: &mut PulseAPI,
+    info: &VolumeInfo,
+    target: &Target,
+    change: VolumeChange,
+    scale: Scale,
+    max: f64,
+) -> Result<(), ControlError> {
+    let adjust = |channels: &mut ChannelVolumes| {
+        let current = scale.to_percent(channels.avg());
+        let percent = limit_percent(change.apply(current), current, max);
+        channels.set(channels.len(), scale.to_volume(percent));
+    };
+    match resolve(info, target)? {
+        Resolved::Sink(sink) => {
+            let mut channels = sink.volume;
+            adjust(&mut channels);
+            api.set_sink_volume(sink.index, &channels)?;
+        }
+        Resolved::SinkInputs(inputs) => {
+            for input in inputs {
+                let mut channels = input.volume;
+                adjust(&mut channels);
+                api.set_sink_input_volume(input.index, &channels)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn set_mute(
+    api: &mut PulseAPI,
+    info: &VolumeInfo,
+    target: &Target,
+    action: MuteAction,
+) -> Result<(), ControlError> {
+    match resolve(info, target)? {
+        Resolved::Sink(sink) => api.set_sink_mute(sink.index, action.apply(sink.mute))?,
+        Resolved::SinkInputs(inputs) => {
+            for input in inputs {
+                api.set_sink_input_mute(input.index, action.apply(input.mute))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Move every stream the `input` target resolves to onto a sink
+pub fn move_inputs(
+    api: &mut PulseAPI,
+    info: &VolumeInfo,
+    input: &Target,
+    sink: &Selector,
+) -> Result<(), ControlError> {
+    let sink = find_sink(info, sink)?;
+    let Resolved::SinkInputs(inputs) = resolve(info, input)? else {
+        return Err(ControlError::NotAStream(input.to_string()));
+    };
+    for input in inputs {
+        api.move_sink_input(input.index, sink.index)?;
+    }
+    Ok(())
+}
+
+pub fn set_default(
+    api: &mut PulseAPI,
+    info: &VolumeInfo,
+    sink: &Selector,
+) -> Result<(), ControlError> {
+    let sink = find_sink(info, sink)?;
+    let name = sink
+        .name
+        .as_deref()
+        .ok_or_else(|| ControlError::NotFound(format!("name of sink:{}", sink.index)))?;
+    api.set_default_sink(name)?;
+    Ok(())
+}
+
+/// Disconnect every stream or client the target resolves to
+pub fn kill(api: &mut PulseAPI, target: &KillTarget) -> Result<(), ControlError> {
+    let indices: Vec<u32> = match target {
+        KillTarget::SinkInput(selector) => api
+            .get_volume_info()?
+            .find_sink_inputs(selector)
+            .iter()
+            .map(|i| i.index)
+            .collect(),
+        KillTarget::SourceOutput(selector) => api
+            .get_recording_info()?
+            .source_outputs()
+            .filter(|o| selector.matches_source_output(o))
+            .map(|o| o.index)
+            .collect(),
+        KillTarget::Client(selector) => api
+            .get_client_list()?
+            .iter()
+            .filter(|c| selector.matches_client(c))
+            .map(|c| c.index)
+            .collect(),
+    };
+    if indices.is_empty() {
+        return Err(ControlError::NotFound(target.to_string()));
+    }
+    for index in indices {
+        match target {
+            KillTarget::SinkInput(_) => api.kill_sink_input(index)?,
+            KillTarget::SourceOutput(_) => api.kill_source_output(index)?,
+            KillTarget::Client(_) => api.kill_client(index)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absolute_and_relative_changes() {
+        assert_eq!("50".parse(), Ok(VolumeChange::Set(50.0)));
+        assert_eq!("50%".parse(), Ok(VolumeChange::Set(50.0)));
+        assert_eq!("+5".parse(), Ok(VolumeChange::Adjust(5.0)));
+        assert_eq!("-10%".parse(), Ok(VolumeChange::Adjust(-10.0)));
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_percentage() {
+        for text in ["", "%", "loud", "5%%x", "nan", "inf", "-inf%", "+NaN"] {
+            assert_eq!(
+                text.parse::<VolumeChange>(),
+                Err(format!("'{text}' is not a volume percentage")),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn applies_to_the_current_volume() {
+        assert_eq!(VolumeChange::Set(30.0).apply(80.0), 30.0);
+        assert_eq!(VolumeChange::Adjust(-5.0).apply(80.0), 75.0);
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        for change in [VolumeChange::Set(40.0), VolumeChange::Adjust(-2.5)] {
+            assert_eq!(change.to_string().parse(), Ok(change));
+        }
+    }
+}
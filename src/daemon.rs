@@ -0,0 +1,205 @@
+//! A long running process holding one connection to the server, controlled over a socket
+//!
+//! `tmix daemon` listens on a Unix domain socket, `$XDG_RUNTIME_DIR/tmix.sock` unless the
+//! config says otherwise. Without a runtime directory it falls back to `tmix.sock` in a
+//! directory of its own under the temporary directory, readable by nobody else. Either way the
+//! daemon refuses to listen in a directory other users can write to, and clients only talk to
+//! a socket owned by the same user. Clients write one JSON request per line and get one JSON
+//! response per line back:
+//!
+//! ```text
+//! {"cmd": "list"}
+//! {"cmd": "get", "target": "sink:default"}
+//! {"cmd": "set", "target": "input:Firefox", "volume": "+5"}
+//! {"cmd": "mute", "target": "sink:default", "action": "toggle"}   // or "on", "off"
+//! {"cmd": "move", "input": "input:12", "sink": "headset"}
+//! {"cmd": "default", "sink": "headset"}
+//! {"cmd": "kill", "target": "client:Firefox"}                   // or "input:", "output:"
+//! {"cmd": "save_scene", "name": "meeting"}
+//! {"cmd": "apply_scene", "name": "meeting"}
+//! {"cmd": "subscribe", "target": "sink:default"}                 // target is optional
+//! ```
+//!
+//! Responses are `{"ok": true}`, with a `data` field for `list` and `get`, or
+//! `{"ok": false, "error": string, "code": int}` where `code` is the exit status the command
+//! line reports for the same error. After a successful `subscribe` the connection carries one
+//! change event per line, in the format printed by `tmix watch`. When the server fails to
+//! answer in time the daemon keeps running and sends `{"event": "error", "error": string}`
+//! instead; the changes it missed follow once the server answers again.
+//!
+//! The daemon also applies the config's rules to new streams. While it runs, the command line
+//! and the mixer send it the requests above rather than connecting to the server themselves.
+mod server;
+
+use std::{
+    env, fmt, fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{fs::MetadataExt, net::UnixStream},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    control::{MuteAction, VolumeChange},
+    target::{KillTarget, Selector, Target},
+};
+
+pub use server::serve;
+
+/// Error codes in responses, matching the command line's exit statuses
+pub const SERVER_ERROR: u8 = 1;
+pub const INVALID_REQUEST: u8 = 2;
+pub const NOT_FOUND: u8 = 3;
+pub const SCENE_ERROR: u8 = 4;
+
+/// How long a client waits for the daemon to answer
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where the daemon listens when the config doesn't say
+pub fn default_socket_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Path::new(&dir).join("tmix.sock"),
+        None => env::temp_dir()
+            .join(format!("tmix-{}", current_uid()))
+            .join("tmix.sock"),
+    }
+}
+
+fn current_uid() -> u32 {
+    // SAFETY: getuid has no preconditions and can't fail
+    unsafe { libc::getuid() }
+}
+
+/// Fail unless `path` belongs to the user running tmix
+fn check_owner(path: &Path) -> io::Result<()> {
+    match fs::metadata(path)?.uid() == current_uid() {
+        true => Ok(()),
+        false => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} belongs to another user", path.display()),
+        )),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Request {
+    List,
+    Get {
+        target: Target,
+    },
+    Set {
+        target: Target,
+        volume: VolumeChange,
+    },
+    Mute {
+        target: Target,
+        #[serde(default = "toggle")]
+        action: MuteAction,
+    },
+    Move {
+        input: Target,
+        sink: Selector,
+    },
+    Default {
+        sink: Selector,
+    },
+    Kill {
+        target: KillTarget,
+    },
+    SaveScene {
+        name: String,
+    },
+    ApplyScene {
+        name: String,
+    },
+    Subscribe {
+        #[serde(default)]
+        target: Option<Target>,
+    },
+}
+
+fn toggle() -> MuteAction {
+    MuteAction::Toggle
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<u8>,
+}
+
+impl Response {
+    pub fn success(data: Option<Value>) -> Self {
+        Self {
+            ok: true,
+            data,
+            error: None,
+            code: None,
+        }
+    }
+
+    pub fn failure(error: impl fmt::Display, code: u8) -> Self {
+        Self {
+            ok: false,
+            data: None,
+            error: Some(error.to_string()),
+            code: Some(code),
+        }
+    }
+}
+
+/// A connection to a running daemon
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    /// Connect to the daemon listening at `path`. Fails straight away when no daemon is
+    /// running, or the socket is another user's, so callers can fall back to talking to the
+    /// server themselves.
+    pub fn connect(path: &Path) -> io::Result<Self> {
+        check_owner(path)?;
+        let writer = UnixStream::connect(path)?;
+        writer.set_read_timeout(Some(TIMEOUT))?;
+        writer.set_write_timeout(Some(TIMEOUT))?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self { reader, writer })
+    }
+
+    /// Send a request and wait for the daemon's response
+    pub fn request(&mut self, request: &Request) -> io::Result<Response> {
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        let line = self.read_line()?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    /// Wait for the next change event, after a successful `subscribe` request
+    pub fn next_event(&mut self) -> io::Result<Value> {
+        self.writer.set_read_timeout(None)?;
+        let line = self.read_line()?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line)? {
+            0 => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the daemon closed the connection",
+            )),
+            _ => Ok(line),
+        }
+    }
+}
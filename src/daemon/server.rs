@@ -0,0 +1,352 @@
+//! The daemon's side of the socket
+//!
+//! The connection to the server isn't thread safe, so it stays on the thread that calls
+//! [`serve`]. Every client gets a thread that reads its requests and hands them over a channel;
+//! the main thread answers them in between checking the server for changes.
+use std::{
+    fs::{self, DirBuilder},
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+
+use pulse::context::subscribe::{Facility, InterestMaskSet, Operation as SubscriptionOperation};
+use serde_json::{json, Value};
+
+use super::{
+    check_owner, Request, Response, INVALID_REQUEST, NOT_FOUND, SCENE_ERROR, SERVER_ERROR,
+};
+use crate::{
+    config::Config,
+    control::{self, ControlError},
+    pulse_api::{PulseAPI, VolumeInfo},
+    rules,
+    scene::{Scene, SceneError},
+    target::Target,
+    watch::ChangeEvent,
+};
+
+/// How long to wait for requests before checking the server for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// How long to wait for a client to take an answer or event
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A line read from a client, along with where to send the answer
+struct Incoming {
+    request: Result<Request, String>,
+    stream: UnixStream,
+}
+
+struct Subscriber {
+    stream: UnixStream,
+    target: Option<Target>,
+}
+
+/// Listen on `path` and serve clients until the connection to the server is lost
+pub fn serve(config: &Config, path: &Path) -> io::Result<()> {
+    let listener = bind(path)?;
+    let mut api = PulseAPI::new();
+    api.set_timeouts(config.server.timeouts());
+    api.startup_connection(config.server.address.as_deref())?;
+    api.subscribe(InterestMaskSet::SINK | InterestMaskSet::SINK_INPUT | InterestMaskSet::SERVER)?;
+    // A server slow to answer now may answer later, which the first check for changes retries
+    let info = match api.get_volume_info() {
+        Ok(info) => Some(info),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => return Err(e),
+        Err(e) => {
+            log::warn!("{e}");
+            None
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || accept(listener, sender));
+    log::info!("Listening on {}", path.display());
+
+    let mut daemon = Daemon {
+        api,
+        config,
+        pending: info.is_none().then(Vec::new),
+        info,
+        subscribers: vec![],
+    };
+    let result = daemon.run(receiver);
+    daemon.api.shutdown();
+    let _ = fs::remove_file(path);
+    result
+}
+
+/// Bind the socket, replacing a socket left behind by a daemon that didn't shut down cleanly.
+///
+/// Anyone who can connect can change the mixer, so the socket is only made in a directory that
+/// belongs to the user and that nobody else can write to, and only the user can connect to it.
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        check_owner(dir)?;
+        if fs::metadata(dir)?.mode() & 0o022 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("other users can write to {}", dir.display()),
+            ));
+        }
+    }
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("a daemon is already listening on {}", path.display()),
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+fn accept(listener: UnixListener, sender: Sender<Incoming>) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let sender = sender.clone();
+                thread::spawn(move || read_requests(stream, sender));
+            }
+            Err(e) => log::warn!("Failed to accept a client: {e}"),
+        }
+    }
+}
+
+/// Forward each line the client sends until it hangs up
+fn read_requests(stream: UnixStream, sender: Sender<Incoming>) -> io::Result<()> {
+    // A client that stops reading mustn't hold up the others
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let reader = BufReader::new(stream.try_clone()?);
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let incoming = Incoming {
+            request: serde_json::from_str(&line).map_err(|e| e.to_string()),
+            stream: stream.try_clone()?,
+        };
+        if sender.send(incoming).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Daemon<'a> {
+    api: PulseAPI,
+    config: &'a Config,
+    /// The state of the server the last time it changed, unless it has never answered
+    info: Option<VolumeInfo>,
+    /// Streams created since subscribers were last told about changes, `None` when they are up
+    /// to date
+    pending: Option<Vec<u32>>,
+    subscribers: Vec<Subscriber>,
+}
+
+impl<'a> Daemon<'a> {
+    fn run(&mut self, receiver: Receiver<Incoming>) -> io::Result<()> {
+        loop {
+            match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(incoming) => self.answer(incoming),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::other("stopped accepting clients"));
+                }
+            }
+            // Only a lost connection is fatal, a server that was too slow to answer gets asked
+            // again next time round
+            if let Err(e) = self.check_changes() {
+                if e.kind() == io::ErrorKind::ConnectionAborted {
+                    return Err(e);
+                }
+                log::warn!("{e}");
+                self.report(&e);
+            }
+        }
+    }
+
+    /// Pass on the changes the server reported, along with any that couldn't be passed on
+    /// before
+    fn check_changes(&mut self) -> io::Result<()> {
+        let changes = self.api.poll_changes()?;
+        if !changes.is_empty() {
+            let new_inputs = changes.iter().filter(|n| {
+                n.facility == Facility::SinkInput && n.operation == SubscriptionOperation::New
+            });
+            self.pending
+                .get_or_insert_with(Vec::new)
+                .extend(new_inputs.map(|n| n.index));
+        }
+        let Some(new_inputs) = self.pending.take() else {
+            return Ok(());
+        };
+        self.changed(&new_inputs)
+            .inspect_err(|_| self.pending = Some(new_inputs))
+    }
+
+    /// Tell subscribers something went wrong, as changes may be reported late
+    fn report(&mut self, error: &io::Error) {
+        let event = json!({"event": "error", "error": error.to_string()});
+        self.subscribers
+            .retain_mut(|subscriber| send(&mut subscriber.stream, &event).is_ok());
+    }
+
+    fn answer(&mut self, incoming: Incoming) {
+        let Incoming {
+            request,
+            mut stream,
+        } = incoming;
+        let response = match request {
+            Ok(Request::Subscribe { target }) => {
+                self.subscribers.push(Subscriber {
+                    stream: match stream.try_clone() {
+                        Ok(stream) => stream,
+                        Err(e) => return log::warn!("Failed to subscribe a client: {e}"),
+                    },
+                    target,
+                });
+                Response::success(None)
+            }
+            Ok(request) => self.handle(request),
+            Err(e) => Response::failure(format!("invalid request: {e}"), INVALID_REQUEST),
+        };
+        if let Err(e) = send(&mut stream, &response) {
+            log::warn!("Failed to answer a client: {e}");
+        }
+    }
+
+    fn handle(&mut self, request: Request) -> Response {
+        let api = &mut self.api;
+        let result = match request {
+            Request::SaveScene { name } => {
+                return match save_scene(api, &name) {
+                    Ok(()) => Response::success(None),
+                    Err(e) => Response::failure(&e, scene_code(&e)),
+                }
+            }
+            Request::ApplyScene { name } => {
+                return match Scene::load(&name).and_then(|scene| scene.apply(api)) {
+                    Ok(()) => Response::success(None),
+                    Err(e) => Response::failure(&e, scene_code(&e)),
+                }
+            }
+            request => self.control(request),
+        };
+
+        match result {
+            Ok(data) => Response::success(data),
+            Err(e) => {
+                let code = match e {
+                    ControlError::NotFound(_) => NOT_FOUND,
+                    ControlError::NotAStream(_) => INVALID_REQUEST,
+                    ControlError::Pulse(_) => SERVER_ERROR,
+                };
+                Response::failure(e, code)
+            }
+        }
+    }
+
+    /// Carry out a request that controls the mixer, only listing the server's sinks for the
+    /// requests that look at them
+    fn control(&mut self, request: Request) -> Result<Option<Value>, ControlError> {
+        let scale = self.config.general.scale;
+        let api = &mut self.api;
+        match request {
+            Request::List => {
+                let info = api.get_volume_info()?;
+                Ok(Some(serde_json::to_value(&info).map_err(io::Error::from)?))
+            }
+            Request::Get { target } => {
+                let info = api.get_volume_info()?;
+                let volumes = control::volumes(&info, &target, scale)?
+                    .into_iter()
+                    .map(|(volume, mute)| json!({"volume": volume, "mute": mute}))
+                    .collect();
+                Ok(Some(Value::Array(volumes)))
+            }
+            Request::Set { target, volume } => {
+                let info = api.get_volume_info()?;
+                let max = self.config.general.max_volume;
+                control::set_volume(api, &info, &target, volume, scale, max).map(|_| None)
+            }
+            Request::Mute { target, action } => {
+                let info = api.get_volume_info()?;
+                control::set_mute(api, &info, &target, action).map(|_| None)
+            }
+            Request::Move { input, sink } => {
+                let info = api.get_volume_info()?;
+                control::move_inputs(api, &info, &input, &sink).map(|_| None)
+            }
+            Request::Default { sink } => {
+                let info = api.get_volume_info()?;
+                control::set_default(api, &info, &sink).map(|_| None)
+            }
+            Request::Kill { target } => control::kill(api, &target).map(|_| None),
+            Request::SaveScene { .. } | Request::ApplyScene { .. } => {
+                unreachable!("scenes are handled by handle")
+            }
+            Request::Subscribe { .. } => unreachable!("subscriptions are handled by answer"),
+        }
+    }
+
+    /// Tell subscribers what changed, and apply the rules to new streams
+    fn changed(&mut self, new_inputs: &[u32]) -> io::Result<()> {
+        let current = self.api.get_volume_info()?;
+        let scale = self.config.general.scale;
+        if let Some(previous) = &self.info {
+            let events = ChangeEvent::diff(previous, &current, scale);
+            self.subscribers.retain_mut(|subscriber| {
+                events
+                    .iter()
+                    .filter(|event| {
+                        subscriber
+                            .target
+                            .as_ref()
+                            .is_none_or(|t| event.concerns(t, previous, &current))
+                    })
+                    .try_for_each(|event| send(&mut subscriber.stream, event))
+                    .is_ok()
+            });
+        }
+
+        let inputs = new_inputs.iter().filter_map(|i| current.sink_input(*i));
+        rules::apply_matching(&mut self.api, &current, inputs, &self.config.rules, scale);
+        self.info = Some(current);
+        Ok(())
+    }
+}
+
+fn save_scene(api: &mut PulseAPI, name: &str) -> Result<(), SceneError> {
+    let info = api.get_volume_info()?;
+    let server = api.get_server_information()?;
+    Scene::capture(&info, &server).save(name)?;
+    Ok(())
+}
+
+fn scene_code(e: &SceneError) -> u8 {
+    match e {
+        SceneError::InvalidName(_) => INVALID_REQUEST,
+        SceneError::NotFound(_) => NOT_FOUND,
+        SceneError::Pulse(_) => SERVER_ERROR,
+        _ => SCENE_ERROR,
+    }
+}
+
+/// Write a value to a client as a single line
+fn send(stream: &mut UnixStream, value: &impl serde::Serialize) -> io::Result<()> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
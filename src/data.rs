@@ -1,14 +1,84 @@
 //! Data Structures for Pulse Audio
+mod serialize;
+
 use pulse::{
     channelmap,
-    context::introspect::{SinkInfo, SinkInputInfo},
+    context::introspect::{
+        CardInfo, CardPortInfo, CardProfileInfo, ClientInfo, ModuleInfo, ServerInfo, SinkInfo,
+        SinkInputInfo, SinkPortInfo, SourceInfo, SourceOutputInfo, SourcePortInfo,
+    },
     def, format,
-    proplist::Proplist,
+    proplist::{properties, Proplist},
     sample,
     time::MicroSeconds,
-    volume::{ChannelVolumes, Volume},
+    volume::{ChannelVolumes, Volume, VolumeLinear},
 };
+use serde::{Deserialize, Serialize};
+
+/// Convert a volume to the percentage shown to users
+pub fn volume_to_percent(volume: Volume) -> f64 {
+    VolumeLinear::from(volume).0 * 100.0
+}
+
+/// The loudest volume tmix sets, in percent. Anything above 100% amplifies the signal.
+pub const MAX_PERCENT: f64 = 150.0;
+
+/// Convert a user facing percentage to a volume, clamped between silence and [`MAX_PERCENT`]
+pub fn percent_to_volume(percent: f64) -> Volume {
+    VolumeLinear((percent / 100.0).clamp(0.0, MAX_PERCENT / 100.0)).into()
+}
+
+/// Limit a volume changed from `current` percent to at most `max` percent. A volume something
+/// else already raised above `max` can stay there, but not go any higher.
+pub fn limit_percent(percent: f64, current: f64, max: f64) -> f64 {
+    percent.clamp(0.0, max.max(current))
+}
+
+/// How volumes are turned into the percentages shown to users
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scale {
+    /// Linear amplitude, where 50% is half the signal
+    #[default]
+    Linear,
+    /// PulseAudio's own cubic scale, the percentages shown by pactl and pavucontrol
+    Cubic,
+}
+
+impl Scale {
+    pub fn to_percent(self, volume: Volume) -> f64 {
+        match self {
+            Scale::Linear => volume_to_percent(volume),
+            Scale::Cubic => volume.0 as f64 / Volume::NORMAL.0 as f64 * 100.0,
+        }
+    }
+
+    /// Convert a percentage on this scale to a volume, clamped between silence and
+    /// [`MAX_PERCENT`]
+    pub fn to_volume(self, percent: f64) -> Volume {
+        match self {
+            Scale::Linear => percent_to_volume(percent),
+            Scale::Cubic => {
+                let fraction = (percent / 100.0).clamp(0.0, MAX_PERCENT / 100.0);
+                Volume((fraction * Volume::NORMAL.0 as f64).round() as u32)
+            }
+        }
+    }
+}
+
+/// Whether `query` appears, ignoring case, in one of the names or in a property value
+fn contains_text(query: &str, names: &[&Option<String>], proplist: &Proplist) -> bool {
+    let query = query.to_lowercase();
+    let contains = |text: &str| text.to_lowercase().contains(&query);
+    names
+        .iter()
+        .any(|name| name.as_deref().is_some_and(contains))
+        || proplist
+            .iter()
+            .any(|key| proplist.get_str(&key).is_some_and(|value| contains(&value)))
+}
 
+#[derive(Serialize, Clone)]
 pub struct SinkInputInformation {
     /// Index of the sink input.
     pub index: u32,
@@ -23,15 +93,20 @@ pub struct SinkInputInformation {
     /// Index of the connected sink.
     pub sink: u32,
     /// The sample specification of the sink input.
+    #[serde(serialize_with = "serialize::sample_spec")]
     pub sample_spec: sample::Spec,
     /// Channel map.
+    #[serde(serialize_with = "serialize::channel_map")]
     pub channel_map: channelmap::Map,
     /// The volume of this sink input.
+    #[serde(serialize_with = "serialize::channel_volumes")]
     pub volume: ChannelVolumes,
     /// Latency due to buffering in sink input, see [`TimingInfo`](crate::def::TimingInfo) for
     /// details.
+    #[serde(serialize_with = "serialize::micros")]
     pub buffer_usec: MicroSeconds,
     /// Latency of the sink device, see [`TimingInfo`](crate::def::TimingInfo) for details.
+    #[serde(serialize_with = "serialize::micros")]
     pub sink_usec: MicroSeconds,
     /// The resampling method used by this sink input.
     pub resample_method: Option<String>,
@@ -40,6 +115,7 @@ pub struct SinkInputInformation {
     /// Stream muted.
     pub mute: bool,
     /// Property list.
+    #[serde(serialize_with = "serialize::proplist")]
     pub proplist: Proplist,
     /// Stream corked.
     pub corked: bool,
@@ -50,37 +126,46 @@ pub struct SinkInputInformation {
     /// control the volume.
     pub volume_writable: bool,
     /// Stream format information.
+    #[serde(serialize_with = "serialize::format_info")]
     pub format: format::Info,
 }
 
+impl SinkInputInformation {
+    /// Whether the search text appears in the stream's name or properties
+    pub fn matches_text(&self, query: &str) -> bool {
+        contains_text(query, &[&self.name], &self.proplist)
+    }
+}
+
 impl From<&SinkInputInfo<'_>> for SinkInputInformation {
     fn from(value: &SinkInputInfo<'_>) -> Self {
         let name = value.name.as_ref().map(|x| x.to_string());
         let resample_method = value.resample_method.as_ref().map(|x| x.to_string());
         let driver = value.driver.as_ref().map(|x| x.to_string());
         Self {
-            index: value.index.clone(),
+            index: value.index,
             name,
-            owner_module: value.owner_module.clone(),
-            client: value.client.clone(),
-            sink: value.sink.clone(),
-            sample_spec: value.sample_spec.clone(),
-            channel_map: value.channel_map.clone(),
-            volume: value.volume.clone(),
-            buffer_usec: value.buffer_usec.clone(),
-            sink_usec: value.sink_usec.clone(),
+            owner_module: value.owner_module,
+            client: value.client,
+            sink: value.sink,
+            sample_spec: value.sample_spec,
+            channel_map: value.channel_map,
+            volume: value.volume,
+            buffer_usec: value.buffer_usec,
+            sink_usec: value.sink_usec,
             resample_method,
             driver,
-            mute: value.mute.clone(),
+            mute: value.mute,
             proplist: value.proplist.clone(),
-            corked: value.corked.clone(),
-            has_volume: value.has_volume.clone(),
-            volume_writable: value.volume_writable.clone(),
+            corked: value.corked,
+            has_volume: value.has_volume,
+            volume_writable: value.volume_writable,
             format: value.format.clone(),
         }
     }
 }
 
+#[derive(Serialize, Clone)]
 pub struct SinkInformation {
     /// Name of the sink.
     pub name: Option<String>,
@@ -89,12 +174,15 @@ pub struct SinkInformation {
     /// Description of this sink.
     pub description: Option<String>,
     /// Sample spec of this sink.
+    #[serde(serialize_with = "serialize::sample_spec")]
     pub sample_spec: sample::Spec,
     /// Channel map.
+    #[serde(serialize_with = "serialize::channel_map")]
     pub channel_map: channelmap::Map,
     /// Index of the owning module of this sink, or `None` if is invalid.
     pub owner_module: Option<u32>,
     /// Volume of the sink.
+    #[serde(serialize_with = "serialize::channel_volumes")]
     pub volume: ChannelVolumes,
     /// Mute switch of the sink.
     pub mute: bool,
@@ -103,26 +191,74 @@ pub struct SinkInformation {
     /// The name of the monitor source.
     pub monitor_source_name: Option<String>,
     /// Length of queued audio in the output buffer.
+    #[serde(serialize_with = "serialize::micros")]
     pub latency: MicroSeconds,
     /// Driver name.
     pub driver: Option<String>,
     /// Flags.
+    #[serde(serialize_with = "serialize::sink_flags")]
     pub flags: def::SinkFlagSet,
     /// Property list.
+    #[serde(serialize_with = "serialize::proplist")]
     pub proplist: Proplist,
     /// The latency this device has been configured to.
+    #[serde(serialize_with = "serialize::micros")]
     pub configured_latency: MicroSeconds,
     /// Some kind of “base” volume that refers to unamplified/unattenuated volume in the context of
     /// the output device.
+    #[serde(serialize_with = "serialize::volume")]
     pub base_volume: Volume,
     /// State.
+    #[serde(serialize_with = "serialize::sink_state")]
     pub state: def::SinkState,
     /// Number of volume steps for sinks which do not support arbitrary volumes.
     pub n_volume_steps: u32,
     /// Card index, or `None` if invalid.
     pub card: Option<u32>,
     /// Set of formats supported by the sink.
+    #[serde(serialize_with = "serialize::format_infos")]
     pub formats: Vec<format::Info>,
+    /// Ports the sink can output to.
+    pub ports: Vec<PortInformation>,
+    /// Port the sink is currently using.
+    pub active_port: Option<PortInformation>,
+}
+
+impl SinkInformation {
+    /// Whether the search text appears in the sink's name, description or properties
+    pub fn matches_text(&self, query: &str) -> bool {
+        contains_text(query, &[&self.name, &self.description], &self.proplist)
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct PortInformation {
+    /// Name of the port.
+    pub name: Option<String>,
+    /// Description of the port.
+    pub description: Option<String>,
+    /// The higher this value is, the more useful this port is as a default.
+    pub priority: u32,
+}
+
+impl From<&SinkPortInfo<'_>> for PortInformation {
+    fn from(value: &SinkPortInfo<'_>) -> Self {
+        Self {
+            name: value.name.as_ref().map(|x| x.to_string()),
+            description: value.description.as_ref().map(|x| x.to_string()),
+            priority: value.priority,
+        }
+    }
+}
+
+impl From<&SourcePortInfo<'_>> for PortInformation {
+    fn from(value: &SourcePortInfo<'_>) -> Self {
+        Self {
+            name: value.name.as_ref().map(|x| x.to_string()),
+            description: value.description.as_ref().map(|x| x.to_string()),
+            priority: value.priority,
+        }
+    }
 }
 
 impl<'a> From<&SinkInfo<'a>> for SinkInformation {
@@ -134,25 +270,358 @@ impl<'a> From<&SinkInfo<'a>> for SinkInformation {
 
         Self {
             name,
-            index: value.index.clone(),
+            index: value.index,
             description,
-            sample_spec: value.sample_spec.clone(),
-            channel_map: value.channel_map.clone(),
-            owner_module: value.owner_module.clone(),
-            volume: value.volume.clone(),
-            mute: value.mute.clone(),
-            monitor_source: value.monitor_source.clone(),
+            sample_spec: value.sample_spec,
+            channel_map: value.channel_map,
+            owner_module: value.owner_module,
+            volume: value.volume,
+            mute: value.mute,
+            monitor_source: value.monitor_source,
             monitor_source_name,
-            latency: value.latency.clone(),
+            latency: value.latency,
             driver,
-            flags: value.flags.clone(),
+            flags: value.flags,
             proplist: value.proplist.clone(),
-            configured_latency: value.configured_latency.clone(),
-            base_volume: value.base_volume.clone(),
-            state: value.state.clone(),
-            n_volume_steps: value.n_volume_steps.clone(),
-            card: value.card.clone(),
+            configured_latency: value.configured_latency,
+            base_volume: value.base_volume,
+            state: value.state,
+            n_volume_steps: value.n_volume_steps,
+            card: value.card,
             formats: value.formats.clone(),
+            ports: value.ports.iter().map(PortInformation::from).collect(),
+            active_port: value.active_port.as_deref().map(PortInformation::from),
+        }
+    }
+}
+
+pub struct SourceInformation {
+    /// Name of the source.
+    pub name: Option<String>,
+    /// Index of the source.
+    pub index: u32,
+    /// Description of this source.
+    pub description: Option<String>,
+    /// Volume of the source.
+    pub volume: ChannelVolumes,
+    /// Mute switch of the source.
+    pub mute: bool,
+    /// Index of the sink this source monitors, or `None` when it isn't a monitor.
+    pub monitor_of_sink: Option<u32>,
+    /// Driver name.
+    pub driver: Option<String>,
+    /// Property list.
+    pub proplist: Proplist,
+    /// Card index, or `None` if invalid.
+    pub card: Option<u32>,
+    /// Ports the source can record from.
+    pub ports: Vec<PortInformation>,
+    /// Port the source is currently using.
+    pub active_port: Option<PortInformation>,
+}
+
+impl SourceInformation {
+    /// Whether the search text appears in the source's name, description or properties
+    pub fn matches_text(&self, query: &str) -> bool {
+        contains_text(query, &[&self.name, &self.description], &self.proplist)
+    }
+}
+
+impl From<&SourceInfo<'_>> for SourceInformation {
+    fn from(value: &SourceInfo<'_>) -> Self {
+        Self {
+            name: value.name.as_ref().map(|x| x.to_string()),
+            index: value.index,
+            description: value.description.as_ref().map(|x| x.to_string()),
+            volume: value.volume,
+            mute: value.mute,
+            monitor_of_sink: value.monitor_of_sink,
+            driver: value.driver.as_ref().map(|x| x.to_string()),
+            proplist: value.proplist.clone(),
+            card: value.card,
+            ports: value.ports.iter().map(PortInformation::from).collect(),
+            active_port: value.active_port.as_deref().map(PortInformation::from),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CardProfile {
+    /// Name of this profile.
+    pub name: Option<String>,
+    /// Description of this profile.
+    pub description: Option<String>,
+    /// The higher this value is, the more useful this profile is as a default.
+    pub priority: u32,
+    /// Whether the profile can currently be used.
+    pub available: bool,
+}
+
+impl From<&CardProfileInfo<'_>> for CardProfile {
+    fn from(value: &CardProfileInfo<'_>) -> Self {
+        Self {
+            name: value.name.as_ref().map(|x| x.to_string()),
+            description: value.description.as_ref().map(|x| x.to_string()),
+            priority: value.priority,
+            available: value.available,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CardPort {
+    /// Name of this port.
+    pub name: Option<String>,
+    /// Description of this port.
+    pub description: Option<String>,
+    /// Latency offset of the port that gets added to the sink/source latency when the port is
+    /// active, in microseconds.
+    pub latency_offset: i64,
+}
+
+impl From<&CardPortInfo<'_>> for CardPort {
+    fn from(value: &CardPortInfo<'_>) -> Self {
+        Self {
+            name: value.name.as_ref().map(|x| x.to_string()),
+            description: value.description.as_ref().map(|x| x.to_string()),
+            latency_offset: value.latency_offset,
+        }
+    }
+}
+
+pub struct CardInformation {
+    /// Index of this card.
+    pub index: u32,
+    /// Name of this card.
+    pub name: Option<String>,
+    /// Driver name.
+    pub driver: Option<String>,
+    /// Property list.
+    pub proplist: Proplist,
+    /// Profiles the card can be switched to.
+    pub profiles: Vec<CardProfile>,
+    /// Profile the card is using.
+    pub active_profile: Option<CardProfile>,
+    /// Ports of the card's sinks and sources.
+    pub ports: Vec<CardPort>,
+}
+
+impl CardInformation {
+    /// Whether the search text appears in the card's name, profiles or properties
+    pub fn matches_text(&self, query: &str) -> bool {
+        let profiles = self.profiles.iter().map(|p| &p.description);
+        let names: Vec<&Option<String>> = [&self.name].into_iter().chain(profiles).collect();
+        contains_text(query, &names, &self.proplist)
+    }
+
+    /// Find one of the card's ports by name
+    pub fn port(&self, name: &str) -> Option<&CardPort> {
+        self.ports.iter().find(|p| p.name.as_deref() == Some(name))
+    }
+
+    /// Name shown to users, the card's description when it has one
+    pub fn display_name(&self) -> String {
+        self.proplist
+            .get_str(properties::DEVICE_DESCRIPTION)
+            .or_else(|| self.name.clone())
+            .unwrap_or_else(|| format!("Card {}", self.index))
+    }
+}
+
+impl From<&CardInfo<'_>> for CardInformation {
+    fn from(value: &CardInfo<'_>) -> Self {
+        Self {
+            index: value.index,
+            name: value.name.as_ref().map(|x| x.to_string()),
+            driver: value.driver.as_ref().map(|x| x.to_string()),
+            proplist: value.proplist.clone(),
+            profiles: value.profiles.iter().map(CardProfile::from).collect(),
+            active_profile: value.active_profile.as_deref().map(CardProfile::from),
+            ports: value.ports.iter().map(CardPort::from).collect(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SourceOutputInformation {
+    /// Index of the source output.
+    pub index: u32,
+    /// Name of the source output.
+    pub name: Option<String>,
+    /// Index of the module this source output belongs to, or `None` when it does not belong to
+    /// any module.
+    pub owner_module: Option<u32>,
+    /// Index of the client this source output belongs to, or `None` when it does not belong to
+    /// any client.
+    pub client: Option<u32>,
+    /// Index of the connected source.
+    pub source: u32,
+    /// The sample specification of the source output.
+    pub sample_spec: sample::Spec,
+    /// Channel map.
+    pub channel_map: channelmap::Map,
+    /// The volume of this source output.
+    pub volume: ChannelVolumes,
+    /// Latency due to buffering in the source output.
+    pub buffer_usec: MicroSeconds,
+    /// Latency of the source device.
+    pub source_usec: MicroSeconds,
+    /// The resampling method used by this source output.
+    pub resample_method: Option<String>,
+    /// Driver name.
+    pub driver: Option<String>,
+    /// Stream muted.
+    pub mute: bool,
+    /// Property list.
+    pub proplist: Proplist,
+    /// Stream corked.
+    pub corked: bool,
+    /// Stream has volume. If not set, then the meaning of this struct’s volume member is
+    /// unspecified.
+    pub has_volume: bool,
+    /// The volume can be set. If not set, the volume can still change even though clients can’t
+    /// control the volume.
+    pub volume_writable: bool,
+    /// Stream format information.
+    pub format: format::Info,
+}
+
+impl SourceOutputInformation {
+    /// Whether the search text appears in the stream's name or properties
+    pub fn matches_text(&self, query: &str) -> bool {
+        contains_text(query, &[&self.name], &self.proplist)
+    }
+}
+
+impl From<&SourceOutputInfo<'_>> for SourceOutputInformation {
+    fn from(value: &SourceOutputInfo<'_>) -> Self {
+        let name = value.name.as_ref().map(|x| x.to_string());
+        let resample_method = value.resample_method.as_ref().map(|x| x.to_string());
+        let driver = value.driver.as_ref().map(|x| x.to_string());
+        Self {
+            index: value.index,
+            name,
+            owner_module: value.owner_module,
+            client: value.client,
+            source: value.source,
+            sample_spec: value.sample_spec,
+            channel_map: value.channel_map,
+            volume: value.volume,
+            buffer_usec: value.buffer_usec,
+            source_usec: value.source_usec,
+            resample_method,
+            driver,
+            mute: value.mute,
+            proplist: value.proplist.clone(),
+            corked: value.corked,
+            has_volume: value.has_volume,
+            volume_writable: value.volume_writable,
+            format: value.format.clone(),
+        }
+    }
+}
+
+pub struct ModuleInformation {
+    /// Index of the module.
+    pub index: u32,
+    /// Name of the module.
+    pub name: Option<String>,
+    /// Argument string of the module.
+    pub argument: Option<String>,
+    /// Usage counter, or `None` if the server doesn't know it.
+    pub n_used: Option<u32>,
+    /// Property list.
+    pub proplist: Proplist,
+}
+
+impl ModuleInformation {
+    /// Whether the search text appears in the module's name, arguments or properties
+    pub fn matches_text(&self, query: &str) -> bool {
+        contains_text(query, &[&self.name, &self.argument], &self.proplist)
+    }
+}
+
+impl From<&ModuleInfo<'_>> for ModuleInformation {
+    fn from(value: &ModuleInfo<'_>) -> Self {
+        Self {
+            index: value.index,
+            name: value.name.as_ref().map(|x| x.to_string()),
+            argument: value.argument.as_ref().map(|x| x.to_string()),
+            n_used: value.n_used,
+            proplist: value.proplist.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ClientInformation {
+    /// Index of this client.
+    pub index: u32,
+    /// Name of this client.
+    pub name: Option<String>,
+    /// Index of the owning module, or `None`.
+    pub owner_module: Option<u32>,
+    /// Driver name.
+    pub driver: Option<String>,
+    /// Property list.
+    pub proplist: Proplist,
+}
+
+impl ClientInformation {
+    /// The process id of the client, if the client reported one
+    pub fn process_id(&self) -> Option<u32> {
+        self.proplist
+            .get_str(properties::APPLICATION_PROCESS_ID)
+            .and_then(|pid| pid.parse().ok())
+    }
+
+    /// Human readable name of the client, preferring the application name
+    pub fn display_name(&self) -> String {
+        self.proplist
+            .get_str(properties::APPLICATION_NAME)
+            .or_else(|| self.name.clone())
+            .unwrap_or_else(|| format!("Client {}", self.index))
+    }
+}
+
+impl From<&ClientInfo<'_>> for ClientInformation {
+    fn from(value: &ClientInfo<'_>) -> Self {
+        let name = value.name.as_ref().map(|x| x.to_string());
+        let driver = value.driver.as_ref().map(|x| x.to_string());
+        Self {
+            index: value.index,
+            name,
+            owner_module: value.owner_module,
+            driver,
+            proplist: value.proplist.clone(),
+        }
+    }
+}
+
+pub struct ServerInformation {
+    /// User name of the daemon process.
+    pub user_name: Option<String>,
+    /// Host name the daemon is running on.
+    pub host_name: Option<String>,
+    /// Version string of the daemon.
+    pub server_version: Option<String>,
+    /// Server package name (usually “pulseaudio”).
+    pub server_name: Option<String>,
+    /// Name of default sink.
+    pub default_sink_name: Option<String>,
+    /// Name of default source.
+    pub default_source_name: Option<String>,
+}
+
+impl From<&ServerInfo<'_>> for ServerInformation {
+    fn from(value: &ServerInfo<'_>) -> Self {
+        Self {
+            user_name: value.user_name.as_ref().map(|x| x.to_string()),
+            host_name: value.host_name.as_ref().map(|x| x.to_string()),
+            server_version: value.server_version.as_ref().map(|x| x.to_string()),
+            server_name: value.server_name.as_ref().map(|x| x.to_string()),
+            default_sink_name: value.default_sink_name.as_ref().map(|x| x.to_string()),
+            default_source_name: value.default_source_name.as_ref().map(|x| x.to_string()),
         }
     }
 }
@@ -0,0 +1,128 @@
+//! Serialisation of PulseAudio types that don't implement `Serialize` themselves
+//!
+//! These are used with `#[serde(serialize_with)]` so the structures in [`crate::data`] can be
+//! written out as JSON or YAML. The shapes produced here are part of the documented output
+//! schema, see [`crate::pulse_api::VolumeInfo`], so change them with care.
+use pulse::{
+    channelmap::{self, Position},
+    def::{SinkFlagSet, SinkState},
+    format,
+    proplist::Proplist,
+    sample,
+    time::MicroSeconds,
+    volume::{ChannelVolumes, Volume},
+};
+use serde::{
+    ser::{SerializeMap, SerializeSeq, SerializeStruct},
+    Serialize, Serializer,
+};
+
+use super::volume_to_percent;
+
+/// `{"format": "s16le", "rate": 44100, "channels": 2}`
+pub(crate) fn sample_spec<S: Serializer>(spec: &sample::Spec, s: S) -> Result<S::Ok, S::Error> {
+    let mut st = s.serialize_struct("SampleSpec", 3)?;
+    st.serialize_field("format", &spec.format.to_string())?;
+    st.serialize_field("rate", &spec.rate)?;
+    st.serialize_field("channels", &spec.channels)?;
+    st.end()
+}
+
+/// `["front-left", "front-right"]`
+pub(crate) fn channel_map<S: Serializer>(map: &channelmap::Map, s: S) -> Result<S::Ok, S::Error> {
+    let positions = map.get();
+    let mut seq = s.serialize_seq(Some(positions.len()))?;
+    for position in positions {
+        seq.serialize_element(&Position::to_string(*position))?;
+    }
+    seq.end()
+}
+
+/// `{"raw": [65536, 65536], "percent": [100.0, 100.0]}`, with percentages on the linear scale
+pub(crate) fn channel_volumes<S: Serializer>(
+    volumes: &ChannelVolumes,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    let channels = volumes.get();
+    let raw: Vec<u32> = channels.iter().map(|v| v.0).collect();
+    let percent: Vec<f64> = channels.iter().map(|v| volume_to_percent(*v)).collect();
+    let mut st = s.serialize_struct("ChannelVolumes", 2)?;
+    st.serialize_field("raw", &raw)?;
+    st.serialize_field("percent", &percent)?;
+    st.end()
+}
+
+/// `{"raw": 65536, "percent": 100.0}`
+pub(crate) fn volume<S: Serializer>(volume: &Volume, s: S) -> Result<S::Ok, S::Error> {
+    let mut st = s.serialize_struct("Volume", 2)?;
+    st.serialize_field("raw", &volume.0)?;
+    st.serialize_field("percent", &volume_to_percent(*volume))?;
+    st.end()
+}
+
+/// Microseconds as a plain integer
+pub(crate) fn micros<S: Serializer>(micros: &MicroSeconds, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u64(micros.0)
+}
+
+/// A map of property names to their string values
+pub(crate) fn proplist<S: Serializer>(proplist: &Proplist, s: S) -> Result<S::Ok, S::Error> {
+    let mut keys: Vec<String> = proplist.iter().collect();
+    keys.sort();
+    let mut map = s.serialize_map(Some(keys.len()))?;
+    for key in keys {
+        let value = proplist.get_str(&key).or_else(|| {
+            proplist
+                .get(&key)
+                .map(|b| String::from_utf8_lossy(b).into())
+        });
+        map.serialize_entry(&key, &value)?;
+    }
+    map.end()
+}
+
+/// `{"encoding": "pcm", "properties": {...}}`
+pub(crate) fn format_info<S: Serializer>(info: &format::Info, s: S) -> Result<S::Ok, S::Error> {
+    FormatInfo(info).serialize(s)
+}
+
+/// A list of [`format_info`] objects
+pub(crate) fn format_infos<S: Serializer>(infos: &[format::Info], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(infos.iter().map(FormatInfo))
+}
+
+struct FormatInfo<'a>(&'a format::Info);
+
+impl Serialize for FormatInfo<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        struct Properties<'a>(&'a Proplist);
+        impl Serialize for Properties<'_> {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                proplist(self.0, s)
+            }
+        }
+
+        let mut st = s.serialize_struct("FormatInfo", 2)?;
+        st.serialize_field(
+            "encoding",
+            &format::Encoding::to_string(self.0.get_encoding()),
+        )?;
+        st.serialize_field("properties", &Properties(self.0.get_properties()))?;
+        st.end()
+    }
+}
+
+/// `["hardware", "decibel_volume"]`
+pub(crate) fn sink_flags<S: Serializer>(flags: &SinkFlagSet, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(flags.iter_names().map(|(name, _)| name.to_lowercase()))
+}
+
+/// One of `"running"`, `"idle"`, `"suspended"` or `"invalid"`
+pub(crate) fn sink_state<S: Serializer>(state: &SinkState, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(match state {
+        SinkState::Running => "running",
+        SinkState::Idle => "idle",
+        SinkState::Suspended => "suspended",
+        SinkState::Invalid => "invalid",
+    })
+}
@@ -1,3 +1,11 @@
-pub mod pulse_api;
+pub mod config;
+pub mod control;
+pub mod daemon;
 pub mod data;
-
+pub mod pulse_api;
+pub mod rules;
+pub mod scene;
+pub mod status;
+pub mod target;
+pub mod virtual_device;
+pub mod watch;
@@ -1,14 +1,37 @@
 mod app;
+mod cli;
 
-use std::io::Result;
+use std::process::ExitCode;
+
+use clap::Parser;
 
 use app::App;
+use cli::Cli;
+use tmix::config::Config;
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     simple_logger::SimpleLogger::new().env().init().unwrap();
 
+    let cli = Cli::parse();
+    let config = match Config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("tmix: {e}");
+            return ExitCode::from(cli::CONFIG_ERROR);
+        }
+    };
+
+    if let Some(command) = cli.command {
+        return cli::run(command, cli.no_daemon, &config);
+    }
+
     // Setup Connection to Pulse
-    let mut applicaton = App::try_new()?;
-    applicaton.run()?;
-    Ok(())
+    let result = App::try_new(config).and_then(|mut applicaton| applicaton.run());
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("tmix: {e}");
+            ExitCode::FAILURE
+        }
+    }
 }
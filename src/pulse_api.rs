@@ -1,26 +1,76 @@
 //! Code to communicate with Pulse Server
+mod model;
+mod ops;
+mod worker;
+
 use pulse::{
     callbacks::ListResult,
     context::{
-        introspect::{SinkInfo, SinkInputInfo},
+        introspect::{
+            CardInfo, ClientInfo, ModuleInfo, ServerInfo, SinkInfo, SinkInputInfo, SourceInfo,
+            SourceOutputInfo,
+        },
+        subscribe::{Facility, InterestMaskSet, Operation as SubscriptionOperation},
         Context, FlagSet as ContextFlagSet,
     },
     def::Retval,
     mainloop::standard::{IterateResult, Mainloop},
     operation::Operation,
     proplist::Proplist,
+    time::MicroSeconds,
+    volume::{ChannelVolumes, Volume},
 };
 
 use std::{
-    cell::{RefCell, RefMut},
-    collections::{hash_map, HashMap},
-    io::Result as IOResult,
+    cell::{Cell, RefCell, RefMut},
+    collections::{btree_map, BTreeMap, HashMap},
+    io::{Error as IOError, ErrorKind, Result as IOResult},
     rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::data::{
+    CardInformation, ClientInformation, ModuleInformation, ServerInformation, SinkInformation,
+    SinkInputInformation, SourceInformation, SourceOutputInformation,
 };
+use crate::target::Selector;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 
-use crate::data::{SinkInformation, SinkInputInformation};
+pub use model::{Diff, Entity, Model};
+use ops::Ops;
+pub use worker::{Job, Pending, Worker};
+
+/// How long the server gets to answer before it's given up on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// To accept the connection
+    pub connect: Duration,
+    /// To answer each request
+    pub operation: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(5),
+            operation: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Results filled in by an introspection callback
+type Collected<T> = Rc<RefCell<Vec<T>>>;
+type SinkInfoListOp = Operation<dyn FnMut(ListResult<&SinkInfo>)>;
+type SinkInputInfoListOp = Operation<dyn FnMut(ListResult<&SinkInputInfo>)>;
+type SourceOutputInfoListOp = Operation<dyn FnMut(ListResult<&SourceOutputInfo>)>;
+type SourceInfoListOp = Operation<dyn FnMut(ListResult<&SourceInfo>)>;
+type CardInfoListOp = Operation<dyn FnMut(ListResult<&CardInfo>)>;
+type ModuleInfoListOp = Operation<dyn FnMut(ListResult<&ModuleInfo>)>;
+type ClientInfoListOp = Operation<dyn FnMut(ListResult<&ClientInfo>)>;
+type ServerInfoOp = Operation<dyn FnMut(&ServerInfo)>;
 
 /// Connects Sinks and their Input information
+#[derive(Serialize)]
 pub struct SinkAndInputs {
     sink: SinkInformation,
     sink_inputs: Vec<SinkInputInformation>,
@@ -39,20 +89,86 @@ impl SinkAndInputs {
         self.sink_inputs.push(value)
     }
 
-    pub fn iter(&self) -> std::slice::Iter<SinkInputInformation>{
+    pub fn iter(&self) -> std::slice::Iter<'_, SinkInputInformation> {
         self.sink_inputs.iter()
-
     }
 }
 
+/// Sinks and the streams playing on them
+///
+/// Serialises to the schema used by `tmix list --format json|yaml`. Fields are only ever added
+/// to this schema; when an existing field changes meaning `schema_version` is bumped.
+///
+/// ```text
+/// {
+///   "schema_version": 1,
+///   "default_sink_name": string | null,
+///   "sinks": [                          // ordered by sink index
+///     {
+///       "sink": {
+///         "name": string | null, "index": int, "description": string | null,
+///         "sample_spec": {"format": string, "rate": int, "channels": int},
+///         "channel_map": [string],       // channel positions, e.g. "front-left"
+///         "owner_module": int | null,
+///         "volume": {"raw": [int], "percent": [float]},
+///         "mute": bool, "monitor_source": int, "monitor_source_name": string | null,
+///         "latency": int,                // microseconds
+///         "driver": string | null,
+///         "flags": [string],             // e.g. "hardware", "decibel_volume"
+///         "proplist": {string: string},
+///         "configured_latency": int,     // microseconds
+///         "base_volume": {"raw": int, "percent": float},
+///         "state": "running" | "idle" | "suspended" | "invalid",
+///         "n_volume_steps": int, "card": int | null,
+///         "formats": [{"encoding": string, "properties": {string: string}}],
+///         "ports": [{"name": string | null, "description": string | null, "priority": int}],
+///         "active_port": {"name": ..., "description": ..., "priority": ...} | null
+///       },
+///       "sink_inputs": [
+///         {
+///           "index": int, "name": string | null, "owner_module": int | null,
+///           "client": int | null, "sink": int,
+///           "sample_spec": {...}, "channel_map": [string],
+///           "volume": {"raw": [int], "percent": [float]},
+///           "buffer_usec": int, "sink_usec": int,
+///           "resample_method": string | null, "driver": string | null,
+///           "mute": bool, "proplist": {string: string}, "corked": bool,
+///           "has_volume": bool, "volume_writable": bool,
+///           "format": {"encoding": string, "properties": {string: string}}
+///         }
+///       ]
+///     }
+///   ]
+/// }
+/// ```
+///
+/// Volume percentages are always on the linear scale, where 100 is full volume, whatever scale
+/// the mixer is configured to show. The `raw` values convert to any other scale.
 pub struct VolumeInfo {
-    sinks_and_inputs: HashMap<u32, SinkAndInputs>,
+    sinks_and_inputs: BTreeMap<u32, SinkAndInputs>,
+    default_sink_name: Option<String>,
 }
 
+/// Version of the [`VolumeInfo`] serialisation schema
+pub const SCHEMA_VERSION: u32 = 1;
+
+impl Serialize for VolumeInfo {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut st = s.serialize_struct("VolumeInfo", 3)?;
+        st.serialize_field("schema_version", &SCHEMA_VERSION)?;
+        st.serialize_field("default_sink_name", &self.default_sink_name)?;
+        st.serialize_field("sinks", &self.sinks_and_inputs.values().collect::<Vec<_>>())?;
+        st.end()
+    }
+}
 
 impl VolumeInfo {
-    fn new(sinks: Vec<SinkInformation>, input_info: Vec<SinkInputInformation>) -> Self {
-        let mut sinks_and_inputs: HashMap<u32, SinkAndInputs> = HashMap::new();
+    pub(crate) fn new(
+        sinks: Vec<SinkInformation>,
+        input_info: Vec<SinkInputInformation>,
+        default_sink_name: Option<String>,
+    ) -> Self {
+        let mut sinks_and_inputs: BTreeMap<u32, SinkAndInputs> = BTreeMap::new();
 
         for sink in sinks {
             sinks_and_inputs.insert(sink.index, SinkAndInputs::new(sink, vec![]));
@@ -65,20 +181,232 @@ impl VolumeInfo {
             // If the sink doesn't exist, that seems like an issue in Pulse Audio, We aren't going
             // to display it
         }
-        Self { sinks_and_inputs }
+        Self {
+            sinks_and_inputs,
+            default_sink_name,
+        }
     }
 
-    pub fn iter(&self) -> hash_map::Iter<u32, SinkAndInputs> {
-        // FIXME: Unorderd!
+    /// Iterate over the sinks, ordered by index
+    pub fn iter(&self) -> btree_map::Iter<'_, u32, SinkAndInputs> {
         self.sinks_and_inputs.iter()
     }
+
+    /// Find a sink by its index
+    pub fn sink(&self, index: u32) -> Option<&SinkInformation> {
+        self.sinks_and_inputs.get(&index).map(|s| s.sink())
+    }
+
+    /// Find a sink input by its index
+    pub fn sink_input(&self, index: u32) -> Option<&SinkInputInformation> {
+        self.sink_inputs().find(|i| i.index == index)
+    }
+
+    /// Iterate over every sink input, grouped by sink
+    pub fn sink_inputs(&self) -> impl Iterator<Item = &SinkInputInformation> {
+        self.sinks_and_inputs.values().flat_map(|s| s.iter())
+    }
+
+    /// Name of the server's default sink
+    pub fn default_sink_name(&self) -> Option<&str> {
+        self.default_sink_name.as_deref()
+    }
+
+    /// Whether the sink is the server's default sink
+    pub fn is_default(&self, sink: &SinkInformation) -> bool {
+        self.default_sink_name.is_some() && sink.name == self.default_sink_name
+    }
+
+    /// Find the first sink picked out by the selector
+    pub fn find_sink(&self, selector: &Selector) -> Option<&SinkInformation> {
+        self.sinks_and_inputs
+            .values()
+            .map(|s| s.sink())
+            .find(|s| selector.matches_sink(s, self.default_sink_name()))
+    }
+
+    /// Find every sink input picked out by the selector
+    pub fn find_sink_inputs(&self, selector: &Selector) -> Vec<&SinkInputInformation> {
+        self.sink_inputs()
+            .filter(|i| selector.matches_sink_input(i))
+            .collect()
+    }
+}
+
+/// Sources and the streams recording from them
+pub struct RecordingInfo {
+    sources: BTreeMap<u32, SourceInformation>,
+    source_outputs: Vec<SourceOutputInformation>,
+    default_source_name: Option<String>,
+}
+
+impl RecordingInfo {
+    /// Iterate over the sources, ordered by index, including the monitors of sinks
+    pub fn sources(&self) -> btree_map::Values<'_, u32, SourceInformation> {
+        self.sources.values()
+    }
+
+    /// Find a source by its index
+    pub fn source(&self, index: u32) -> Option<&SourceInformation> {
+        self.sources.get(&index)
+    }
+
+    /// Iterate over every source output, ordered by the source it records from
+    pub fn source_outputs(&self) -> std::slice::Iter<'_, SourceOutputInformation> {
+        self.source_outputs.iter()
+    }
+
+    /// Whether the source is the server's default source
+    pub fn is_default(&self, source: &SourceInformation) -> bool {
+        self.default_source_name.is_some() && source.name == self.default_source_name
+    }
+}
+
+/// How streams are attributed to an application: by the process that owns the client when
+/// the client reports one, otherwise by the client itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ApplicationKey {
+    Process(u32),
+    Client(u32),
+}
+
+/// All the streams belonging to a single client process
+#[derive(Clone)]
+pub struct Application {
+    clients: Vec<ClientInformation>,
+    sink_inputs: Vec<SinkInputInformation>,
+    source_outputs: Vec<SourceOutputInformation>,
+}
+
+impl Application {
+    /// Group sink inputs and source outputs by the client process they belong to.
+    ///
+    /// Applications without any streams, and streams without a client, are left out.
+    fn group(
+        clients: Vec<ClientInformation>,
+        sink_inputs: Vec<SinkInputInformation>,
+        source_outputs: Vec<SourceOutputInformation>,
+    ) -> Vec<Self> {
+        let mut client_keys: HashMap<u32, ApplicationKey> = HashMap::new();
+        let mut applications: HashMap<ApplicationKey, Application> = HashMap::new();
+
+        for client in clients {
+            let key = match client.process_id() {
+                Some(pid) => ApplicationKey::Process(pid),
+                None => ApplicationKey::Client(client.index),
+            };
+            client_keys.insert(client.index, key);
+            applications
+                .entry(key)
+                .or_insert_with(|| Application {
+                    clients: vec![],
+                    sink_inputs: vec![],
+                    source_outputs: vec![],
+                })
+                .clients
+                .push(client);
+        }
+
+        for input in sink_inputs {
+            let key = input.client.and_then(|c| client_keys.get(&c));
+            if let Some(app) = key.and_then(|k| applications.get_mut(k)) {
+                app.sink_inputs.push(input);
+            }
+        }
+
+        for output in source_outputs {
+            let key = output.client.and_then(|c| client_keys.get(&c));
+            if let Some(app) = key.and_then(|k| applications.get_mut(k)) {
+                app.source_outputs.push(output);
+            }
+        }
+
+        let mut applications: Vec<Application> = applications
+            .into_values()
+            .filter(|app| !app.sink_inputs.is_empty() || !app.source_outputs.is_empty())
+            .collect();
+        applications.sort_by_key(|app| app.name().to_lowercase());
+        applications
+    }
+
+    /// Name of the application, taken from its first client
+    pub fn name(&self) -> String {
+        self.clients
+            .first()
+            .map(|c| c.display_name())
+            .unwrap_or_default()
+    }
+
+    pub fn clients(&self) -> std::slice::Iter<'_, ClientInformation> {
+        self.clients.iter()
+    }
+
+    pub fn sink_inputs(&self) -> std::slice::Iter<'_, SinkInputInformation> {
+        self.sink_inputs.iter()
+    }
+
+    pub fn source_outputs(&self) -> std::slice::Iter<'_, SourceOutputInformation> {
+        self.source_outputs.iter()
+    }
+
+    /// Master volume of the application, which is the volume of its loudest stream
+    pub fn volume(&self) -> Volume {
+        self.sink_inputs
+            .iter()
+            .map(|i| i.volume.avg())
+            .chain(self.source_outputs.iter().map(|o| o.volume.avg()))
+            .max()
+            .unwrap_or(Volume::MUTED)
+    }
+
+    /// Whether the search text appears in the application's name or any of its streams
+    pub fn matches_text(&self, query: &str) -> bool {
+        self.name().to_lowercase().contains(&query.to_lowercase())
+            || self.sink_inputs.iter().any(|i| i.matches_text(query))
+            || self.source_outputs.iter().any(|o| o.matches_text(query))
+    }
+
+    /// An application is muted only when every one of its streams is muted
+    pub fn mute(&self) -> bool {
+        self.sink_inputs.iter().all(|i| i.mute) && self.source_outputs.iter().all(|o| o.mute)
+    }
 }
 
+/// Scale every channel by how far the master volume moves from `from` to `to`. Channels of a
+/// silent application have nothing to scale from, so they are all set to `to`.
+fn rescale(mut channels: ChannelVolumes, from: Volume, to: Volume) -> ChannelVolumes {
+    if from == Volume::MUTED {
+        channels.set(channels.len(), to);
+        return channels;
+    }
+    for channel in channels.get_mut() {
+        let scaled = u64::from(channel.0) * u64::from(to.0) / u64::from(from.0);
+        channel.0 = scaled.min(u64::from(Volume::MAX.0)) as u32;
+    }
+    channels
+}
+
+/// A change notification sent by the server after [`PulseAPI::subscribe`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Notification {
+    pub facility: Facility,
+    pub operation: SubscriptionOperation,
+    /// Index of the object that changed
+    pub index: u32,
+}
 
 /// Higher Level Pulse API
 pub struct PulseAPI {
     mainloop: Mainloop,
     ctx: Context,
+    notifications: Rc<RefCell<Vec<Notification>>>,
+    timeouts: Timeouts,
+}
+
+impl Default for PulseAPI {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PulseAPI {
@@ -92,30 +420,45 @@ impl PulseAPI {
         let ctx = Context::new_with_proplist(&mainloop, "tmixContext", &proplist)
             .expect("Failed to create new context");
 
-        PulseAPI { mainloop, ctx }
+        PulseAPI {
+            mainloop,
+            ctx,
+            notifications: Rc::new(RefCell::new(vec![])),
+            timeouts: Timeouts::default(),
+        }
+    }
+
+    /// Change how long the server gets to answer, which applies from the next request
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
     }
 
-    pub fn startup_connection(&mut self) -> IOResult<()> {
+    /// Connect to the server at `server`, or the default server when `None`
+    pub fn startup_connection(&mut self, server: Option<&str>) -> IOResult<()> {
         self.ctx
-            .connect(None, ContextFlagSet::NOFLAGS, None)
-            .expect("Failed to connect context");
+            .connect(server, ContextFlagSet::NOFLAGS, None)
+            .map_err(|e| IOError::new(ErrorKind::ConnectionRefused, e))?;
 
         // Wait for context to be ready
+        let deadline = Instant::now() + self.timeouts.connect;
         loop {
-            match self.mainloop.iterate(false) {
-                IterateResult::Quit(_) | IterateResult::Err(_) => {
-                    eprintln!("Iterate state was not success, quitting...");
-                    return Ok(());
-                }
-                IterateResult::Success(_) => {}
+            if Instant::now() >= deadline {
+                self.ctx.disconnect();
+                return Err(timed_out(
+                    "connect to the PulseAudio server",
+                    self.timeouts.connect,
+                ));
             }
+            self.iterate_until(deadline)?;
             match self.ctx.get_state() {
                 pulse::context::State::Ready => {
                     break;
                 }
                 pulse::context::State::Failed | pulse::context::State::Terminated => {
-                    eprintln!("Context state failed/terminated, quitting...");
-                    return Ok(());
+                    return Err(IOError::new(
+                        ErrorKind::ConnectionRefused,
+                        "Could not connect to the PulseAudio server",
+                    ));
                 }
                 _ => {}
             }
@@ -126,39 +469,332 @@ impl PulseAPI {
     pub fn get_volume_info(&mut self) -> IOResult<VolumeInfo> {
         let (inputs_op, sink_inputs) = self.get_sink_inputs()?;
         let (info_op, sink_info) = self.get_sink_info()?;
-
-        self.await_ops((inputs_op, info_op));
+        let (server_op, server_info) = self.get_server_info()?;
+        let mut ops = Ops::new();
+        ops.push(inputs_op).push(info_op).push(server_op);
+        self.await_all(ops)?;
 
         // SAFTEY: It is ok to take because by this point the callbacks have
         // completed and we are ready to move on
-        Ok(VolumeInfo::new(sink_info.take(), sink_inputs.take()))
+        let default_sink_name = server_info.take().and_then(|s| s.default_sink_name);
+        Ok(VolumeInfo::new(
+            sink_info.take(),
+            sink_inputs.take(),
+            default_sink_name,
+        ))
+    }
+
+    /// Get the server's details, including its default sink and source
+    pub fn get_server_information(&mut self) -> IOResult<ServerInformation> {
+        let (server_op, server_info) = self.get_server_info()?;
+        self.await_op(server_op)?;
+        server_info
+            .take()
+            .ok_or_else(|| IOError::other("The server did not describe itself"))
+    }
+
+    /// Get the sources and the streams recording from them
+    pub fn get_recording_info(&mut self) -> IOResult<RecordingInfo> {
+        let (sources_op, sources) = self.get_source_info()?;
+        let (outputs_op, source_outputs) = self.get_source_outputs()?;
+        let (server_op, server_info) = self.get_server_info()?;
+        let mut ops = Ops::new();
+        ops.push(sources_op).push(outputs_op).push(server_op);
+        self.await_all(ops)?;
+
+        let mut source_outputs = source_outputs.take();
+        source_outputs.sort_by_key(|o| (o.source, o.index));
+        Ok(RecordingInfo {
+            sources: sources.take().into_iter().map(|s| (s.index, s)).collect(),
+            source_outputs,
+            default_source_name: server_info.take().and_then(|s| s.default_source_name),
+        })
+    }
+
+    /// Get every client connected to the server, including those without streams
+    pub fn get_client_list(&mut self) -> IOResult<Vec<ClientInformation>> {
+        let (clients_op, clients) = self.get_clients()?;
+        self.await_op(clients_op)?;
+        Ok(clients.take())
+    }
+
+    /// Get the modules loaded into the server, ordered by index
+    pub fn get_modules(&mut self) -> IOResult<Vec<ModuleInformation>> {
+        let (modules_op, modules) = self.get_module_info()?;
+        self.await_op(modules_op)?;
+        let mut modules = modules.take();
+        modules.sort_by_key(|m| m.index);
+        Ok(modules)
+    }
+
+    /// Get the sound cards and the profiles they can be switched to
+    pub fn get_cards(&mut self) -> IOResult<Vec<CardInformation>> {
+        let (cards_op, cards) = self.get_card_info()?;
+        self.await_op(cards_op)?;
+        let mut cards = cards.take();
+        cards.sort_by_key(|c| c.index);
+        Ok(cards)
+    }
+
+    /// Get every application that currently has streams, with its streams grouped together
+    pub fn get_applications(&mut self) -> IOResult<Vec<Application>> {
+        let (clients_op, clients) = self.get_clients()?;
+        let (inputs_op, sink_inputs) = self.get_sink_inputs()?;
+        let (outputs_op, source_outputs) = self.get_source_outputs()?;
+        let mut ops = Ops::new();
+        ops.push(clients_op).push(inputs_op).push(outputs_op);
+        self.await_all(ops)?;
+
+        Ok(Application::group(
+            clients.take(),
+            sink_inputs.take(),
+            source_outputs.take(),
+        ))
+    }
+
+    pub fn set_sink_volume(&mut self, index: u32, volume: &ChannelVolumes) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .set_sink_volume_by_index(index, volume, Some(cb))
+        })
+    }
+
+    pub fn set_sink_mute(&mut self, index: u32, mute: bool) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .set_sink_mute_by_index(index, mute, Some(cb))
+        })
+    }
+
+    pub fn set_sink_input_volume(&mut self, index: u32, volume: &ChannelVolumes) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .set_sink_input_volume(index, volume, Some(cb))
+        })
+    }
+
+    pub fn set_sink_input_mute(&mut self, index: u32, mute: bool) -> IOResult<()> {
+        self.await_success(|ctx, cb| ctx.introspect().set_sink_input_mute(index, mute, Some(cb)))
+    }
+
+    pub fn set_source_output_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .set_source_output_volume(index, volume, Some(cb))
+        })
+    }
+
+    pub fn set_source_output_mute(&mut self, index: u32, mute: bool) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .set_source_output_mute(index, mute, Some(cb))
+        })
+    }
+
+    pub fn set_source_volume(&mut self, index: u32, volume: &ChannelVolumes) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .set_source_volume_by_index(index, volume, Some(cb))
+        })
+    }
+
+    pub fn set_source_mute(&mut self, index: u32, mute: bool) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .set_source_mute_by_index(index, mute, Some(cb))
+        })
+    }
+
+    /// Switch a card to the named profile
+    pub fn set_card_profile(&mut self, index: u32, profile: &str) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .set_card_profile_by_index(index, profile, Some(cb))
+        })
+    }
+
+    /// Delay everything played or recorded through a card's port by `offset` microseconds,
+    /// e.g. to keep audio on a Bluetooth headset in sync with video
+    pub fn set_port_latency_offset(&mut self, card: &str, port: &str, offset: i64) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .set_port_latency_offset(card, port, offset, Some(cb))
+        })
+    }
+
+    /// Load a module with the given arguments, e.g. `module-null-sink` with
+    /// `sink_name=virtual`, and return its index
+    pub fn load_module(&mut self, name: &str, argument: &str) -> IOResult<u32> {
+        let index = Rc::new(Cell::new(None));
+        let index_inner = index.clone();
+        let op = self
+            .ctx
+            .introspect()
+            .load_module(name, argument, move |i| index_inner.set(Some(i)));
+        self.await_op(op)?;
+
+        match index.get() {
+            Some(i) if i != pulse::def::INVALID_INDEX => Ok(i),
+            _ => Err(IOError::other(format!(
+                "PulseAudio could not load {name}: {}",
+                self.last_error()
+            ))),
+        }
+    }
+
+    pub fn unload_module(&mut self, index: u32) -> IOResult<()> {
+        self.await_success(|ctx, cb| ctx.introspect().unload_module(index, cb))
+    }
+
+    /// Disconnect a playback stream
+    pub fn kill_sink_input(&mut self, index: u32) -> IOResult<()> {
+        self.await_success(|ctx, cb| ctx.introspect().kill_sink_input(index, cb))
     }
 
-    /// Await for array of Ops to complete
-    /// This allows us to make sure that all operations and callbacks of passed in ops have
-    /// completed so that we can then safely move on
-    // FIXME: This is a dumb way to do the generics, but for now it works. If i ever
-    // Want to Add more kinds of ops i should take the time to figure it out.
-    fn await_ops<T: ?Sized, U: ?Sized>(&mut self, ops: (Operation<T>, Operation<U>)) {
+    /// Disconnect a recording stream
+    pub fn kill_source_output(&mut self, index: u32) -> IOResult<()> {
+        self.await_success(|ctx, cb| ctx.introspect().kill_source_output(index, cb))
+    }
+
+    /// Disconnect a client, along with all of its streams
+    pub fn kill_client(&mut self, index: u32) -> IOResult<()> {
+        self.await_success(|ctx, cb| ctx.introspect().kill_client(index, cb))
+    }
+
+    /// Move a sink input so it plays on another sink
+    pub fn move_sink_input(&mut self, index: u32, sink_index: u32) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .move_sink_input_by_index(index, sink_index, Some(cb))
+        })
+    }
+
+    /// Move a source output so it records from another source
+    pub fn move_source_output(&mut self, index: u32, source_index: u32) -> IOResult<()> {
+        self.await_success(|ctx, cb| {
+            ctx.introspect()
+                .move_source_output_by_index(index, source_index, Some(cb))
+        })
+    }
+
+    /// Make the named sink the server's default sink
+    pub fn set_default_sink(&mut self, name: &str) -> IOResult<()> {
+        self.await_success(|ctx, cb| ctx.set_default_sink(name, cb))
+    }
+
+    /// Make the named source the server's default source
+    pub fn set_default_source(&mut self, name: &str) -> IOResult<()> {
+        self.await_success(|ctx, cb| ctx.set_default_source(name, cb))
+    }
+
+    /// Move the application's master volume to `volume`, scaling every stream by the same
+    /// factor so each keeps its balance and its level relative to the other streams
+    pub fn set_application_volume(&mut self, app: &Application, volume: Volume) -> IOResult<()> {
+        let master = app.volume();
+        for input in app.sink_inputs().filter(|i| i.volume_writable) {
+            let channels = rescale(input.volume, master, volume);
+            self.set_sink_input_volume(input.index, &channels)?;
+        }
+        for output in app.source_outputs().filter(|o| o.volume_writable) {
+            let channels = rescale(output.volume, master, volume);
+            self.set_source_output_volume(output.index, &channels)?;
+        }
+        Ok(())
+    }
+
+    /// Mute or unmute every stream of the application
+    pub fn set_application_mute(&mut self, app: &Application, mute: bool) -> IOResult<()> {
+        for input in app.sink_inputs() {
+            self.set_sink_input_mute(input.index, mute)?;
+        }
+        for output in app.source_outputs() {
+            self.set_source_output_mute(output.index, mute)?;
+        }
+        Ok(())
+    }
+
+    /// Await a single operation, running the mainloop until it has finished
+    fn await_op<T: ?Sized + 'static>(&mut self, op: Operation<T>) -> IOResult<()> {
+        let mut ops = Ops::new();
+        ops.push(op);
+        self.await_all(ops)
+    }
+
+    /// Run the mainloop until every operation has finished, so their callbacks have filled in
+    /// every result. When the server takes longer than the timeout, or an operation is
+    /// cancelled, the others are cancelled too and an error is returned instead of partial
+    /// results.
+    fn await_all(&mut self, mut ops: Ops) -> IOResult<()> {
+        let deadline = Instant::now() + self.timeouts.operation;
         loop {
-            self.mainloop.iterate(false);
-            match ops.0.get_state() {
-                pulse::operation::State::Done | pulse::operation::State::Cancelled => break,
-                pulse::operation::State::Running => {}
+            if ops.done() {
+                return Ok(());
+            }
+            if ops.failed() {
+                ops.cancel();
+                return Err(IOError::new(
+                    ErrorKind::ConnectionAborted,
+                    "The server cancelled a request",
+                ));
             }
-            match ops.1.get_state() {
-                pulse::operation::State::Done | pulse::operation::State::Cancelled => break,
-                pulse::operation::State::Running => {}
+            if Instant::now() >= deadline {
+                ops.cancel();
+                return Err(timed_out(
+                    "get an answer from the PulseAudio server",
+                    self.timeouts.operation,
+                ));
             }
+            self.iterate_until(deadline)?;
         }
     }
 
-    fn get_sink_info(
-        &mut self,
-    ) -> IOResult<(
-        Operation<dyn FnMut(ListResult<&SinkInfo>)>,
-        Rc<RefCell<Vec<SinkInformation>>>,
-    )> {
+    /// Run one iteration of the mainloop, waiting for events no later than `deadline`
+    fn iterate_until(&mut self, deadline: Instant) -> IOResult<()> {
+        let wait = deadline.saturating_duration_since(Instant::now());
+        let wait = MicroSeconds(wait.as_micros().min(i32::MAX as u128) as u64);
+        self.mainloop
+            .prepare(Some(wait))
+            .and_then(|_| self.mainloop.poll())
+            .and_then(|_| self.mainloop.dispatch())
+            .map(|_| ())
+            .map_err(|e| IOError::other(format!("The mainloop failed: {e}")))
+    }
+
+    /// Start an operation that reports success, and wait for the server's answer
+    fn await_success<F>(&mut self, start: F) -> IOResult<()>
+    where
+        F: FnOnce(&mut Context, Box<dyn FnMut(bool)>) -> Operation<dyn FnMut(bool)>,
+    {
+        let success = Rc::new(Cell::new(false));
+        let success_inner = success.clone();
+        let op = start(
+            &mut self.ctx,
+            Box::new(move |ok: bool| success_inner.set(ok)),
+        );
+        self.await_op(op)?;
+
+        match success.get() {
+            true => Ok(()),
+            false => Err(IOError::other(format!(
+                "PulseAudio rejected the operation: {}",
+                self.last_error()
+            ))),
+        }
+    }
+
+    /// Description of the last error reported by the server
+    fn last_error(&self) -> String {
+        self.ctx
+            .errno()
+            .to_string()
+            .unwrap_or_else(|| "unknown error".to_string())
+    }
+
+    fn get_sink_info(&mut self) -> IOResult<(SinkInfoListOp, Collected<SinkInformation>)> {
         let introspector = self.ctx.introspect();
         let results: Rc<RefCell<Vec<SinkInformation>>> = Rc::new(RefCell::new(vec![]));
         let results_inner = results.clone();
@@ -169,19 +805,48 @@ impl PulseAPI {
             }
             pulse::callbacks::ListResult::End => {}
             pulse::callbacks::ListResult::Error => {
-                eprintln!("ERROR: Mr. Robinson");
+                log::error!("Failed to list sinks");
             }
         });
 
         Ok((op, results))
     }
 
+    /// Get one sink, or `None` when there is no sink with the index
+    fn get_sink(&mut self, index: u32) -> IOResult<Option<SinkInformation>> {
+        let result: Rc<RefCell<Option<SinkInformation>>> = Rc::new(RefCell::new(None));
+        let result_inner = result.clone();
+        let op = self
+            .ctx
+            .introspect()
+            .get_sink_info_by_index(index, move |res| {
+                if let ListResult::Item(sink) = res {
+                    result_inner.replace(Some(sink.into()));
+                }
+            });
+        self.await_op(op)?;
+        Ok(result.take())
+    }
+
+    /// Get one sink input, or `None` when there is no sink input with the index
+    fn get_sink_input(&mut self, index: u32) -> IOResult<Option<SinkInputInformation>> {
+        let result: Rc<RefCell<Option<SinkInputInformation>>> = Rc::new(RefCell::new(None));
+        let result_inner = result.clone();
+        let op = self
+            .ctx
+            .introspect()
+            .get_sink_input_info(index, move |res| {
+                if let ListResult::Item(input) = res {
+                    result_inner.replace(Some(input.into()));
+                }
+            });
+        self.await_op(op)?;
+        Ok(result.take())
+    }
+
     fn get_sink_inputs(
         &mut self,
-    ) -> IOResult<(
-        Operation<dyn FnMut(ListResult<&SinkInputInfo>)>,
-        Rc<RefCell<Vec<SinkInputInformation>>>,
-    )> {
+    ) -> IOResult<(SinkInputInfoListOp, Collected<SinkInputInformation>)> {
         let introspector = self.ctx.introspect();
         let results: Rc<RefCell<Vec<SinkInputInformation>>> = Rc::new(RefCell::new(vec![]));
         let results_inner = results.clone();
@@ -194,7 +859,7 @@ impl PulseAPI {
                     }
                     pulse::callbacks::ListResult::End => {}
                     pulse::callbacks::ListResult::Error => {
-                        eprintln!("ERROR: Mr. Robinson");
+                        log::error!("Failed to list sink inputs");
                     }
                 },
             );
@@ -202,9 +867,194 @@ impl PulseAPI {
         Ok((op, results))
     }
 
+    fn get_source_outputs(
+        &mut self,
+    ) -> IOResult<(SourceOutputInfoListOp, Collected<SourceOutputInformation>)> {
+        let introspector = self.ctx.introspect();
+        let results: Collected<SourceOutputInformation> = Rc::new(RefCell::new(vec![]));
+        let results_inner = results.clone();
+        let op =
+            introspector.get_source_output_info_list(move |res: ListResult<&SourceOutputInfo>| {
+                match res {
+                    pulse::callbacks::ListResult::Item(source) => {
+                        let mut r: RefMut<Vec<SourceOutputInformation>> =
+                            results_inner.borrow_mut();
+                        r.push(source.into());
+                    }
+                    pulse::callbacks::ListResult::End => {}
+                    pulse::callbacks::ListResult::Error => {
+                        log::error!("Failed to list source outputs");
+                    }
+                }
+            });
+
+        Ok((op, results))
+    }
+
+    fn get_source_info(&mut self) -> IOResult<(SourceInfoListOp, Collected<SourceInformation>)> {
+        let introspector = self.ctx.introspect();
+        let results: Collected<SourceInformation> = Rc::new(RefCell::new(vec![]));
+        let results_inner = results.clone();
+        let op = introspector.get_source_info_list(move |res: ListResult<&SourceInfo>| match res {
+            pulse::callbacks::ListResult::Item(source) => {
+                results_inner.borrow_mut().push(source.into());
+            }
+            pulse::callbacks::ListResult::End => {}
+            pulse::callbacks::ListResult::Error => {
+                log::error!("Failed to list sources");
+            }
+        });
+
+        Ok((op, results))
+    }
+
+    fn get_module_info(&mut self) -> IOResult<(ModuleInfoListOp, Collected<ModuleInformation>)> {
+        let introspector = self.ctx.introspect();
+        let results: Collected<ModuleInformation> = Rc::new(RefCell::new(vec![]));
+        let results_inner = results.clone();
+        let op = introspector.get_module_info_list(move |res: ListResult<&ModuleInfo>| match res {
+            pulse::callbacks::ListResult::Item(module) => {
+                results_inner.borrow_mut().push(module.into());
+            }
+            pulse::callbacks::ListResult::End => {}
+            pulse::callbacks::ListResult::Error => {
+                log::error!("Failed to list modules");
+            }
+        });
+
+        Ok((op, results))
+    }
+
+    fn get_card_info(&mut self) -> IOResult<(CardInfoListOp, Collected<CardInformation>)> {
+        let introspector = self.ctx.introspect();
+        let results: Collected<CardInformation> = Rc::new(RefCell::new(vec![]));
+        let results_inner = results.clone();
+        let op = introspector.get_card_info_list(move |res: ListResult<&CardInfo>| match res {
+            pulse::callbacks::ListResult::Item(card) => {
+                results_inner.borrow_mut().push(card.into());
+            }
+            pulse::callbacks::ListResult::End => {}
+            pulse::callbacks::ListResult::Error => {
+                log::error!("Failed to list cards");
+            }
+        });
+
+        Ok((op, results))
+    }
+
+    fn get_server_info(
+        &mut self,
+    ) -> IOResult<(ServerInfoOp, Rc<RefCell<Option<ServerInformation>>>)> {
+        let introspector = self.ctx.introspect();
+        let result: Rc<RefCell<Option<ServerInformation>>> = Rc::new(RefCell::new(None));
+        let result_inner = result.clone();
+        let op = introspector.get_server_info(move |info: &ServerInfo| {
+            result_inner.replace(Some(info.into()));
+        });
+
+        Ok((op, result))
+    }
+
+    fn get_clients(&mut self) -> IOResult<(ClientInfoListOp, Collected<ClientInformation>)> {
+        let introspector = self.ctx.introspect();
+        let results: Collected<ClientInformation> = Rc::new(RefCell::new(vec![]));
+        let results_inner = results.clone();
+        let op = introspector.get_client_info_list(move |res: ListResult<&ClientInfo>| match res {
+            pulse::callbacks::ListResult::Item(source) => {
+                let mut r: RefMut<Vec<ClientInformation>> = results_inner.borrow_mut();
+                r.push(source.into());
+            }
+            pulse::callbacks::ListResult::End => {}
+            pulse::callbacks::ListResult::Error => {
+                log::error!("Failed to list clients");
+            }
+        });
+
+        Ok((op, results))
+    }
+
+    /// Ask the server to notify us about changes to the given kinds of objects.
+    ///
+    /// Notifications are collected while the mainloop runs, and handed out by
+    /// [`wait_for_changes`](Self::wait_for_changes).
+    pub fn subscribe(&mut self, mask: InterestMaskSet) -> IOResult<()> {
+        let notifications = self.notifications.clone();
+        self.ctx
+            .set_subscribe_callback(Some(Box::new(move |facility, operation, index| {
+                if let (Some(facility), Some(operation)) = (facility, operation) {
+                    notifications.borrow_mut().push(Notification {
+                        facility,
+                        operation,
+                        index,
+                    });
+                }
+            })));
+        self.await_success(|ctx, cb| ctx.subscribe(mask, cb))
+    }
+
+    /// Block until the server reports at least one change, and return every change reported so
+    /// far. Whenever the server stays quiet for as long as the request timeout it's asked for
+    /// its information, so a connection that stopped answering fails with a timeout rather than
+    /// waiting forever.
+    pub fn wait_for_changes(&mut self) -> IOResult<Vec<Notification>> {
+        loop {
+            let deadline = Instant::now() + self.timeouts.operation;
+            while Instant::now() < deadline {
+                let pending: Vec<Notification> =
+                    self.notifications.borrow_mut().drain(..).collect();
+                if !pending.is_empty() {
+                    return Ok(pending);
+                }
+                self.iterate_until(deadline)?;
+                if let pulse::context::State::Failed | pulse::context::State::Terminated =
+                    self.ctx.get_state()
+                {
+                    return Err(IOError::new(
+                        ErrorKind::ConnectionAborted,
+                        "Lost connection to the PulseAudio server",
+                    ));
+                }
+            }
+            self.get_server_information()?;
+        }
+    }
+
+    /// Run the mainloop without blocking, and return every change reported so far
+    pub fn poll_changes(&mut self) -> IOResult<Vec<Notification>> {
+        loop {
+            match self.mainloop.iterate(false) {
+                IterateResult::Quit(_) | IterateResult::Err(_) => {
+                    return Err(IOError::other("Iterate state was not success"));
+                }
+                IterateResult::Success(0) => break,
+                IterateResult::Success(_) => {}
+            }
+        }
+        if let pulse::context::State::Failed | pulse::context::State::Terminated =
+            self.ctx.get_state()
+        {
+            return Err(IOError::new(
+                ErrorKind::ConnectionAborted,
+                "Lost connection to the PulseAudio server",
+            ));
+        }
+        Ok(self.notifications.borrow_mut().drain(..).collect())
+    }
+
     pub fn shutdown(&mut self) {
         self.ctx.disconnect();
         // Clean shutdown
         self.mainloop.quit(Retval(0)); // uncertain whether this is necessary
     }
 }
+
+/// The error returned when the server took longer than `timeout` to do `what`
+fn timed_out(what: &str, timeout: Duration) -> IOError {
+    IOError::new(
+        ErrorKind::TimedOut,
+        format!(
+            "Timed out after {}s trying to {what}",
+            timeout.as_secs_f64()
+        ),
+    )
+}
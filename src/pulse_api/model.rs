@@ -0,0 +1,196 @@
+//! A long-lived copy of the server's sinks and sink inputs, kept up to date change by change
+//!
+//! Fetching every sink and sink input again each time something might have changed copies
+//! every property list on every refresh. A [`Model`] loads them once, then subscribes to the
+//! server's change notifications and only fetches the objects the server says changed. Each
+//! update that changed something bumps the model's generation, so callers can tell whether what
+//! they last saw is still current without comparing it.
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::Result as IOResult,
+    sync::Arc,
+};
+
+use pulse::context::subscribe::{Facility, InterestMaskSet, Operation as SubscriptionOperation};
+use serde::Serialize;
+
+use super::{Ops, PulseAPI, VolumeInfo};
+use crate::data::{SinkInformation, SinkInputInformation};
+
+/// Something the model keeps, by index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entity {
+    /// The server itself, which names the default sink
+    Server,
+    Sink(u32),
+    SinkInput(u32),
+}
+
+/// How an entity changed in an update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diff {
+    Added(Entity),
+    Changed(Entity),
+    Removed(Entity),
+}
+
+pub struct Model {
+    sinks: BTreeMap<u32, SinkInformation>,
+    sink_inputs: BTreeMap<u32, SinkInputInformation>,
+    default_sink_name: Option<String>,
+    generation: u64,
+    /// Entities reported as changed that haven't been fetched yet, because the server failed
+    /// to answer before they were
+    pending: VecDeque<(Entity, SubscriptionOperation)>,
+    /// The sinks and sink inputs grouped for the rest of tmix, shared until they change
+    info: Option<Arc<VolumeInfo>>,
+}
+
+impl Model {
+    /// Subscribe to changes, then load every sink and sink input. Subscribing first means no
+    /// change made while loading is missed.
+    pub fn load(api: &mut PulseAPI) -> IOResult<Self> {
+        api.subscribe(
+            InterestMaskSet::SINK | InterestMaskSet::SINK_INPUT | InterestMaskSet::SERVER,
+        )?;
+        let (sinks_op, sinks) = api.get_sink_info()?;
+        let (inputs_op, sink_inputs) = api.get_sink_inputs()?;
+        let (server_op, server_info) = api.get_server_info()?;
+        let mut ops = Ops::new();
+        ops.push(sinks_op).push(inputs_op).push(server_op);
+        api.await_all(ops)?;
+
+        Ok(Self {
+            sinks: sinks.take().into_iter().map(|s| (s.index, s)).collect(),
+            sink_inputs: sink_inputs
+                .take()
+                .into_iter()
+                .map(|i| (i.index, i))
+                .collect(),
+            default_sink_name: server_info.take().and_then(|s| s.default_sink_name),
+            generation: 0,
+            pending: VecDeque::new(),
+            info: None,
+        })
+    }
+
+    /// Counts the updates that changed something
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Apply the changes the server reported since the last update, fetching only the entities
+    /// that changed, and return how each of them changed. When the server fails to answer, the
+    /// changes applied so far are kept and the rest are fetched by the next update.
+    pub fn update(&mut self, api: &mut PulseAPI) -> IOResult<Vec<Diff>> {
+        // An entity may be reported several times, only its latest state matters
+        for notification in api.poll_changes()? {
+            let entity = match notification.facility {
+                Facility::Sink => Entity::Sink(notification.index),
+                Facility::SinkInput => Entity::SinkInput(notification.index),
+                Facility::Server => Entity::Server,
+                _ => continue,
+            };
+            match self.pending.iter_mut().find(|(e, _)| *e == entity) {
+                // A new entity that changed since is still new
+                Some((_, SubscriptionOperation::New))
+                    if notification.operation == SubscriptionOperation::Changed => {}
+                Some((_, operation)) => *operation = notification.operation,
+                None => self.pending.push_back((entity, notification.operation)),
+            }
+        }
+
+        let mut diffs = vec![];
+        let result = loop {
+            let Some(&(entity, operation)) = self.pending.front() else {
+                break Ok(());
+            };
+            match self.fetch(api, entity, operation) {
+                Ok(diff) => {
+                    self.pending.pop_front();
+                    diffs.extend(diff);
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        if !diffs.is_empty() {
+            self.generation += 1;
+            self.info = None;
+        }
+        result.map(|()| diffs)
+    }
+
+    /// Bring one entity up to date
+    fn fetch(
+        &mut self,
+        api: &mut PulseAPI,
+        entity: Entity,
+        operation: SubscriptionOperation,
+    ) -> IOResult<Option<Diff>> {
+        Ok(match (entity, operation) {
+            (Entity::Server, _) => {
+                let server = api.get_server_information()?;
+                (self.default_sink_name != server.default_sink_name).then(|| {
+                    self.default_sink_name = server.default_sink_name;
+                    Diff::Changed(Entity::Server)
+                })
+            }
+            (Entity::Sink(index), SubscriptionOperation::Removed) => {
+                self.sinks.remove(&index).map(|_| Diff::Removed(entity))
+            }
+            (Entity::Sink(index), _) => {
+                let sink = api.get_sink(index)?;
+                replace(&mut self.sinks, index, sink, entity)
+            }
+            (Entity::SinkInput(index), SubscriptionOperation::Removed) => self
+                .sink_inputs
+                .remove(&index)
+                .map(|_| Diff::Removed(entity)),
+            (Entity::SinkInput(index), _) => {
+                let input = api.get_sink_input(index)?;
+                replace(&mut self.sink_inputs, index, input, entity)
+            }
+        })
+    }
+
+    /// The sinks and sink inputs, grouped as the rest of tmix expects them. They are only
+    /// copied out of the model again once they change.
+    pub fn volume_info(&mut self) -> Arc<VolumeInfo> {
+        let info = self.info.get_or_insert_with(|| {
+            Arc::new(VolumeInfo::new(
+                self.sinks.values().cloned().collect(),
+                self.sink_inputs.values().cloned().collect(),
+                self.default_sink_name.clone(),
+            ))
+        });
+        info.clone()
+    }
+}
+
+/// Store the entity fetched afresh, or forget it when it was gone by the time it was fetched.
+/// The server also reports changes to things tmix doesn't keep, so an entity that was reported
+/// as changed but looks the same isn't a change.
+fn replace<T: Serialize>(
+    entities: &mut BTreeMap<u32, T>,
+    index: u32,
+    fetched: Option<T>,
+    entity: Entity,
+) -> Option<Diff> {
+    match fetched {
+        Some(value) => match entities.insert(index, value) {
+            Some(old) if same(&old, &entities[&index]) => None,
+            Some(_) => Some(Diff::Changed(entity)),
+            None => Some(Diff::Added(entity)),
+        },
+        None => entities.remove(&index).map(|_| Diff::Removed(entity)),
+    }
+}
+
+/// Whether two entities hold the same values. The server's types can't be compared directly, so
+/// they are compared the way `tmix list --format json` shows them.
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    match (serde_json::to_value(a), serde_json::to_value(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
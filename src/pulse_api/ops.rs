@@ -0,0 +1,67 @@
+//! Waiting on several operations at once
+//!
+//! Every introspection call hands back an [`Operation`] typed by its callback, so a sink list
+//! and a server info request can't share a `Vec`. [`Ops`] hides the callback type, letting a
+//! query start any mix of operations and wait for all of them together.
+use pulse::operation::{Operation, State};
+
+/// An operation with its callback type erased
+trait InFlight {
+    fn state(&self) -> State;
+    fn cancel(&mut self);
+}
+
+impl<T: ?Sized> InFlight for Operation<T> {
+    fn state(&self) -> State {
+        self.get_state()
+    }
+
+    fn cancel(&mut self) {
+        Operation::cancel(self)
+    }
+}
+
+/// Operations of any kinds that are awaited together. Whatever is still running when the set
+/// is dropped is cancelled, so callbacks never fill in results nobody is waiting on.
+#[derive(Default)]
+pub(crate) struct Ops {
+    ops: Vec<Box<dyn InFlight>>,
+}
+
+impl Ops {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an operation to the set
+    pub fn push<T: ?Sized + 'static>(&mut self, op: Operation<T>) -> &mut Self {
+        self.ops.push(Box::new(op));
+        self
+    }
+
+    /// Whether every operation has finished
+    pub fn done(&self) -> bool {
+        self.ops.iter().all(|op| op.state() == State::Done)
+    }
+
+    /// Whether an operation was cancelled, which the server does when the connection is lost,
+    /// leaving its results incomplete
+    pub fn failed(&self) -> bool {
+        self.ops.iter().any(|op| op.state() == State::Cancelled)
+    }
+
+    /// Cancel every operation still running
+    pub fn cancel(&mut self) {
+        for op in &mut self.ops {
+            if op.state() == State::Running {
+                op.cancel();
+            }
+        }
+    }
+}
+
+impl Drop for Ops {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -0,0 +1,109 @@
+//! A connection to the server kept on a thread of its own
+//!
+//! [`PulseAPI`] waits on the server for every request, which is fine for a command but freezes
+//! an interactive program while the server is slow to answer. A [`Worker`] owns the
+//! connection on its own thread and runs requests there in the order they were made, handing
+//! back a [`Pending`] result that can be checked without waiting.
+use std::{
+    io::{Error as IOError, ErrorKind, Result as IOResult},
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread::{self, JoinHandle},
+};
+
+use super::{PulseAPI, Timeouts};
+
+/// Something to run with the worker's connection
+pub type Job<T> = Box<dyn FnOnce(&mut PulseAPI) -> IOResult<T> + Send>;
+
+pub struct Worker {
+    jobs: Option<Sender<Job<()>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    /// Connect to the server at `server`, or the default server when `None`, on a new thread.
+    /// Waits until the connection is made, has failed or timed out.
+    pub fn connect(server: Option<&str>, timeouts: Timeouts) -> IOResult<Self> {
+        let server = server.map(str::to_string);
+        let (jobs, queue) = mpsc::channel::<Job<()>>();
+        let (connected, connection) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("pulse".to_string())
+            .spawn(move || {
+                let mut api = PulseAPI::new();
+                api.set_timeouts(timeouts);
+                let result = api.startup_connection(server.as_deref());
+                let ok = result.is_ok();
+                let _ = connected.send(result);
+                if ok {
+                    // Runs until the worker is dropped, which closes the queue
+                    for job in queue {
+                        let _ = job(&mut api);
+                    }
+                }
+                api.shutdown();
+            })?;
+        connection.recv().map_err(|_| closed())??;
+        Ok(Self {
+            jobs: Some(jobs),
+            thread: Some(thread),
+        })
+    }
+
+    /// Queue a request, returning its result once the worker gets to it
+    pub fn request<T, F>(&self, f: F) -> Pending<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut PulseAPI) -> IOResult<T> + Send + 'static,
+    {
+        let (result, pending) = mpsc::channel();
+        let job: Job<()> = Box::new(move |api| {
+            // Whoever asked may have stopped waiting, which is fine
+            let _ = result.send(f(api));
+            Ok(())
+        });
+        if let Some(jobs) = &self.jobs {
+            // A worker that stopped drops the job, and with it the sender, which the pending
+            // result reports as a closed connection
+            let _ = jobs.send(job);
+        }
+        Pending { result: pending }
+    }
+
+    /// Finish the queued requests, then disconnect from the server
+    pub fn shutdown(&mut self) {
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// The result of a request to a [`Worker`], once the worker has run it
+pub struct Pending<T> {
+    result: Receiver<IOResult<T>>,
+}
+
+impl<T> Pending<T> {
+    /// The result when the request has finished, without waiting for it
+    pub fn try_take(&self) -> Option<IOResult<T>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(closed())),
+        }
+    }
+}
+
+fn closed() -> IOError {
+    IOError::new(
+        ErrorKind::ConnectionAborted,
+        "The connection to the PulseAudio server was closed",
+    )
+}
@@ -0,0 +1,339 @@
+//! Settings applied automatically to streams when they appear
+//!
+//! Rules live in the config file as an array of tables. Each rule matches streams on any of
+//! their application name, process binary and media role, ignoring case, and a stream must
+//! match every one the rule gives. Every matching rule is applied in the order they are
+//! written, so later rules win.
+//!
+//! ```toml
+//! [[rules]]
+//! application = "Spotify"      # application.name
+//! volume = 40                  # percent, on the configured scale
+//!
+//! [[rules]]
+//! binary = "zoom"              # application.process.binary
+//! role = "phone"               # media.role
+//! sink = "alsa_output.usb-headset.analog-stereo"
+//! mute = false
+//! ```
+use std::{fmt, io::Result as IOResult};
+
+use pulse::proplist::properties::{APPLICATION_NAME, APPLICATION_PROCESS_BINARY, MEDIA_ROLE};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    data::{Scale, SinkInputInformation},
+    pulse_api::{PulseAPI, VolumeInfo},
+    target::Selector,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    /// Matches the stream's `application.name`
+    pub application: Option<String>,
+    /// Matches the stream's `application.process.binary`
+    pub binary: Option<String>,
+    /// Matches the stream's `media.role`
+    pub role: Option<String>,
+    /// Volume to set, in percent
+    pub volume: Option<f64>,
+    pub mute: Option<bool>,
+    /// Sink to move the stream to, by name or description
+    pub sink: Option<String>,
+}
+
+impl Rule {
+    /// Whether the rule applies to a stream
+    pub fn matches(&self, input: &SinkInputInformation) -> bool {
+        let property = |expected: &Option<String>, key: &str| match expected {
+            Some(expected) => input
+                .proplist
+                .get_str(key)
+                .is_some_and(|value| value.eq_ignore_ascii_case(expected)),
+            None => true,
+        };
+        property(&self.application, APPLICATION_NAME)
+            && property(&self.binary, APPLICATION_PROCESS_BINARY)
+            && property(&self.role, MEDIA_ROLE)
+    }
+
+    /// Apply the rule's settings to a stream. A sink that doesn't exist is skipped with a
+    /// warning, as the device may simply be unplugged.
+    pub fn apply(
+        &self,
+        api: &mut PulseAPI,
+        info: &VolumeInfo,
+        input: &SinkInputInformation,
+        scale: Scale,
+    ) -> IOResult<()> {
+        if let Some(percent) = self.volume.filter(|_| input.volume_writable) {
+            let mut channels = input.volume;
+            channels.set(channels.len(), scale.to_volume(percent));
+            api.set_sink_input_volume(input.index, &channels)?;
+        }
+        if let Some(mute) = self.mute {
+            api.set_sink_input_mute(input.index, mute)?;
+        }
+        if let Some(name) = &self.sink {
+            match info.find_sink(&Selector::Name(name.clone())) {
+                Some(sink) if sink.index != input.sink => {
+                    api.move_sink_input(input.index, sink.index)?
+                }
+                Some(_) => {}
+                None => log::warn!("Rule refers to sink '{name}', which doesn't exist"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Describe what the rule does, e.g. `volume 40%, move to headset`
+    pub fn actions(&self) -> String {
+        let mut actions = vec![];
+        if let Some(volume) = self.volume {
+            actions.push(format!("volume {volume}%"));
+        }
+        match self.mute {
+            Some(true) => actions.push("mute".to_string()),
+            Some(false) => actions.push("unmute".to_string()),
+            None => {}
+        }
+        if let Some(sink) = &self.sink {
+            actions.push(format!("move to {sink}"));
+        }
+        actions.join(", ")
+    }
+}
+
+/// Written the way the rule's matchers appear in the config, e.g. `application=Spotify`
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let matchers = [
+            ("application", &self.application),
+            ("binary", &self.binary),
+            ("role", &self.role),
+        ];
+        let mut first = true;
+        for (key, value) in matchers {
+            if let Some(value) = value {
+                if !first {
+                    write!(f, " ")?;
+                }
+                write!(f, "{key}={value}")?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The rules that apply to a stream, along with their position in the config
+pub fn matching<'a>(
+    rules: &'a [Rule],
+    input: &'a SinkInputInformation,
+) -> impl Iterator<Item = (usize, &'a Rule)> {
+    rules
+        .iter()
+        .enumerate()
+        .filter(move |(_, rule)| rule.matches(input))
+}
+
+/// Apply every matching rule to each stream. A stream that can't be changed, usually because
+/// it went away already, doesn't stop the others.
+pub fn apply_matching<'a>(
+    api: &mut PulseAPI,
+    info: &VolumeInfo,
+    inputs: impl Iterator<Item = &'a SinkInputInformation>,
+    rules: &[Rule],
+    scale: Scale,
+) {
+    for input in inputs {
+        for (i, rule) in matching(rules, input) {
+            log::info!(
+                "Rule {} ({rule}) applies to input {}: {}",
+                i + 1,
+                input.index,
+                rule.actions()
+            );
+            if let Err(e) = rule.apply(api, info, input, scale) {
+                log::warn!(
+                    "Failed to apply rule {} to input {}: {e}",
+                    i + 1,
+                    input.index
+                );
+            }
+        }
+    }
+}
+
+/// A rule as written in the config, before it's checked
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFields {
+    application: Option<String>,
+    binary: Option<String>,
+    role: Option<String>,
+    volume: Option<f64>,
+    mute: Option<bool>,
+    sink: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = RuleFields::deserialize(deserializer)?;
+        let rule = Rule {
+            application: fields.application,
+            binary: fields.binary,
+            role: fields.role,
+            volume: fields.volume,
+            mute: fields.mute,
+            sink: fields.sink,
+        };
+
+        if rule.application.is_none() && rule.binary.is_none() && rule.role.is_none() {
+            return Err(serde::de::Error::custom(
+                "a rule needs at least one of application, binary or role to match on",
+            ));
+        }
+        if rule.volume.is_none() && rule.mute.is_none() && rule.sink.is_none() {
+            return Err(serde::de::Error::custom(format!(
+                "rule {rule} needs at least one of volume, mute or sink"
+            )));
+        }
+        if let Some(volume) = rule.volume.filter(|v| !(0.0..=100.0).contains(v)) {
+            return Err(serde::de::Error::custom(format!(
+                "rule {rule} has volume {volume}, which must be between 0 and 100"
+            )));
+        }
+        Ok(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pulse::{
+        channelmap, format,
+        proplist::Proplist,
+        sample,
+        time::MicroSeconds,
+        volume::{ChannelVolumes, Volume},
+    };
+
+    use super::*;
+
+    /// The rules of a config file
+    #[derive(Deserialize)]
+    struct Rules {
+        rules: Vec<Rule>,
+    }
+
+    fn parse(text: &str) -> Result<Vec<Rule>, String> {
+        toml::from_str::<Rules>(text)
+            .map(|config| config.rules)
+            .map_err(|e| e.message().to_string())
+    }
+
+    /// A stream playing on `sink`, with the given properties
+    fn sink_input(index: u32, sink: u32, properties: &[(&str, &str)]) -> SinkInputInformation {
+        let mut proplist = Proplist::new().expect("proplist");
+        for (key, value) in properties {
+            proplist.set_str(key, value).expect("property");
+        }
+        let mut volume = ChannelVolumes::default();
+        volume.set_len(2);
+        volume.get_mut().fill(Volume::NORMAL);
+        SinkInputInformation {
+            index,
+            name: None,
+            owner_module: None,
+            client: None,
+            sink,
+            sample_spec: sample::Spec {
+                format: sample::Format::S16le,
+                rate: 44100,
+                channels: 2,
+            },
+            channel_map: channelmap::Map::default(),
+            volume,
+            buffer_usec: MicroSeconds(0),
+            sink_usec: MicroSeconds(0),
+            resample_method: None,
+            driver: None,
+            mute: false,
+            proplist,
+            corked: false,
+            has_volume: true,
+            volume_writable: true,
+            format: format::Info::new().expect("format"),
+        }
+    }
+
+    #[test]
+    fn parses_rules() {
+        let rules = parse(
+            r#"
+            [[rules]]
+            application = "Spotify"
+            volume = 40
+
+            [[rules]]
+            binary = "zoom"
+            role = "phone"
+            sink = "headset"
+            mute = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rules[0].to_string(), "application=Spotify");
+        assert_eq!(rules[0].actions(), "volume 40%");
+        assert_eq!(rules[1].to_string(), "binary=zoom role=phone");
+        assert_eq!(rules[1].actions(), "unmute, move to headset");
+    }
+
+    #[test]
+    fn rejects_rules_that_do_nothing() {
+        assert_eq!(
+            parse("[[rules]]\nvolume = 40"),
+            Err("a rule needs at least one of application, binary or role to match on".into())
+        );
+        assert_eq!(
+            parse("[[rules]]\napplication = \"mpv\""),
+            Err("rule application=mpv needs at least one of volume, mute or sink".into())
+        );
+        assert_eq!(
+            parse("[[rules]]\napplication = \"mpv\"\nvolume = 150"),
+            Err("rule application=mpv has volume 150, which must be between 0 and 100".into())
+        );
+        assert!(parse("[[rules]]\napp = \"mpv\"\nmute = true").is_err());
+    }
+
+    #[test]
+    fn matches_every_property_given_ignoring_case() {
+        let rule =
+            &parse("[[rules]]\napplication = \"zoom\"\nrole = \"phone\"\nmute = true").unwrap()[0];
+        let call = sink_input(1, 0, &[(APPLICATION_NAME, "ZOOM"), (MEDIA_ROLE, "Phone")]);
+        let music = sink_input(2, 0, &[(APPLICATION_NAME, "zoom"), (MEDIA_ROLE, "music")]);
+        let unnamed = sink_input(3, 0, &[(MEDIA_ROLE, "phone")]);
+        assert!(rule.matches(&call));
+        assert!(!rule.matches(&music));
+        assert!(!rule.matches(&unnamed));
+    }
+
+    #[test]
+    fn matching_rules_keep_their_position() {
+        let rules = parse(
+            r#"
+            [[rules]]
+            binary = "firefox"
+            mute = true
+
+            [[rules]]
+            application = "Firefox"
+            volume = 20
+            "#,
+        )
+        .unwrap();
+        let firefox = sink_input(1, 0, &[(APPLICATION_NAME, "Firefox")]);
+        let positions: Vec<usize> = matching(&rules, &firefox).map(|(i, _)| i).collect();
+        assert_eq!(positions, vec![1]);
+    }
+}
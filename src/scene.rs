@@ -0,0 +1,313 @@
+//! Named snapshots of the mixer that can be saved and applied later
+//!
+//! Each scene is stored in its own file, `$XDG_CONFIG_HOME/tmix/scenes/<name>.toml`:
+//!
+//! ```toml
+//! default_sink = "alsa_output.usb-headset.analog-stereo"
+//! default_source = "alsa_input.usb-headset.mono"
+//!
+//! [[sinks]]
+//! name = "alsa_output.usb-headset.analog-stereo"
+//! volume = 80.0
+//! mute = false
+//!
+//! [[applications]]
+//! name = "Firefox"                  # the stream's application.name property
+//! volume = 60.0
+//! mute = false
+//! sink = "alsa_output.usb-headset.analog-stereo"
+//! ```
+//!
+//! Volumes are percentages on the linear scale used by `tmix list --format json`. Every setting
+//! is optional, so a hand written scene only changes what it mentions. Sinks and applications
+//! are matched by name rather than index, as indices change whenever a device is plugged in or
+//! a stream is restarted.
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use pulse::proplist::properties::APPLICATION_NAME;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    config::Config,
+    data::{percent_to_volume, volume_to_percent, ServerInformation, SinkInputInformation},
+    pulse_api::{PulseAPI, VolumeInfo},
+    target::Selector,
+};
+
+#[derive(Debug, Error)]
+pub enum SceneError {
+    #[error("'{0}' is not a valid scene name")]
+    InvalidName(String),
+    #[error("no scene named '{0}'")]
+    NotFound(String),
+    #[error("no directory to keep scenes in, set XDG_CONFIG_HOME or HOME")]
+    NoDirectory,
+    #[error("could not read {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("invalid scene file {path}:\n{source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("could not save {path}: {source}")]
+    Write { path: PathBuf, source: io::Error },
+    #[error("could not encode scene: {0}")]
+    Encode(#[from] toml::ser::Error),
+    #[error(transparent)]
+    Pulse(#[from] io::Error),
+}
+
+/// Default devices, sink volumes and per-application settings to restore together
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_sink: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_source: Option<String>,
+    pub sinks: Vec<SinkSetting>,
+    pub applications: Vec<ApplicationSetting>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SinkSetting {
+    /// The sink's name, or its description
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+}
+
+/// Settings for every playback stream of an application
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApplicationSetting {
+    /// The application name the streams report, matched ignoring case
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+    /// Sink to move the streams to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sink: Option<String>,
+}
+
+impl Scene {
+    /// Directory scenes are saved in
+    pub fn dir() -> Option<PathBuf> {
+        Config::dir().map(|dir| dir.join("scenes"))
+    }
+
+    fn path(name: &str) -> Result<PathBuf, SceneError> {
+        let valid = !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\']);
+        if !valid {
+            return Err(SceneError::InvalidName(name.to_string()));
+        }
+        Self::dir()
+            .map(|dir| dir.join(format!("{name}.toml")))
+            .ok_or(SceneError::NoDirectory)
+    }
+
+    /// Names of the saved scenes, in alphabetical order
+    pub fn names() -> Result<Vec<String>, SceneError> {
+        let dir = Self::dir().ok_or(SceneError::NoDirectory)?;
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(source) => return Err(SceneError::Read { path: dir, source }),
+        };
+
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Read a saved scene
+    pub fn load(name: &str) -> Result<Self, SceneError> {
+        let path = Self::path(name)?;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(SceneError::NotFound(name.to_string()))
+            }
+            Err(source) => return Err(SceneError::Read { path, source }),
+        };
+        toml::from_str(&text).map_err(|source| SceneError::Parse { path, source })
+    }
+
+    /// Save the scene under `name`, replacing any scene already saved with that name
+    pub fn save(&self, name: &str) -> Result<PathBuf, SceneError> {
+        let path = Self::path(name)?;
+        let text = toml::to_string(self)?;
+        let write = |path: &Path| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, text)
+        };
+        write(&path).map_err(|source| SceneError::Write {
+            path: path.clone(),
+            source,
+        })?;
+        Ok(path)
+    }
+
+    /// Remove a saved scene
+    pub fn delete(name: &str) -> Result<(), SceneError> {
+        let path = Self::path(name)?;
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Err(SceneError::NotFound(name.to_string()))
+            }
+            Err(source) => Err(SceneError::Write { path, source }),
+        }
+    }
+
+    /// Capture the current state of the server
+    pub fn capture(info: &VolumeInfo, server: &ServerInformation) -> Self {
+        let sinks = info
+            .iter()
+            .map(|(_, entry)| entry.sink())
+            .filter_map(|sink| {
+                Some(SinkSetting {
+                    name: sink.name.clone()?,
+                    volume: Some(volume_to_percent(sink.volume.avg())),
+                    mute: Some(sink.mute),
+                })
+            })
+            .collect();
+
+        let mut by_application: BTreeMap<String, Vec<&SinkInputInformation>> = BTreeMap::new();
+        for input in info.sink_inputs() {
+            if let Some(name) = input.proplist.get_str(APPLICATION_NAME) {
+                by_application.entry(name).or_default().push(input);
+            }
+        }
+        let applications = by_application
+            .into_iter()
+            .map(|(name, inputs)| ApplicationSetting {
+                name,
+                volume: inputs
+                    .iter()
+                    .map(|i| i.volume.avg())
+                    .max()
+                    .map(volume_to_percent),
+                mute: Some(inputs.iter().all(|i| i.mute)),
+                sink: info.sink(inputs[0].sink).and_then(|s| s.name.clone()),
+            })
+            .collect();
+
+        Self {
+            default_sink: server.default_sink_name.clone(),
+            default_source: server.default_source_name.clone(),
+            sinks,
+            applications,
+        }
+    }
+
+    /// Apply the scene to the server.
+    ///
+    /// Devices come and go and applications may not be running, so settings for anything that
+    /// isn't there, or that can't be changed, are skipped with a warning rather than failing the
+    /// whole scene. Only failing to list what's there fails it.
+    pub fn apply(&self, api: &mut PulseAPI) -> Result<(), SceneError> {
+        let info = api.get_volume_info()?;
+        let find_sink = |name: &str| {
+            let sink = info.find_sink(&Selector::Name(name.to_string()));
+            if sink.is_none() {
+                log::warn!("Scene refers to sink '{name}', which doesn't exist");
+            }
+            sink
+        };
+
+        if let Some(name) = &self.default_sink {
+            if let Some(sink_name) = find_sink(name).and_then(|s| s.name.as_deref()) {
+                if let Err(e) = api.set_default_sink(sink_name) {
+                    log::warn!("Could not make '{sink_name}' the default sink: {e}");
+                }
+            }
+        }
+        if let Some(name) = &self.default_source {
+            if let Err(e) = api.set_default_source(name) {
+                log::warn!("Could not make '{name}' the default source: {e}");
+            }
+        }
+
+        for setting in &self.sinks {
+            let Some(sink) = find_sink(&setting.name) else {
+                continue;
+            };
+            if let Some(percent) = setting.volume {
+                let mut channels = sink.volume;
+                channels.set(channels.len(), percent_to_volume(percent));
+                if let Err(e) = api.set_sink_volume(sink.index, &channels) {
+                    log::warn!("Could not set the volume of '{}': {e}", setting.name);
+                }
+            }
+            if let Some(mute) = setting.mute {
+                if let Err(e) = api.set_sink_mute(sink.index, mute) {
+                    log::warn!("Could not mute or unmute '{}': {e}", setting.name);
+                }
+            }
+        }
+
+        for setting in &self.applications {
+            let inputs: Vec<&SinkInputInformation> = info
+                .sink_inputs()
+                .filter(|i| {
+                    i.proplist
+                        .get_str(APPLICATION_NAME)
+                        .is_some_and(|n| n.eq_ignore_ascii_case(&setting.name))
+                })
+                .collect();
+            if inputs.is_empty() {
+                log::info!("Skipping '{}', it has no streams", setting.name);
+                continue;
+            }
+            let sink = setting.sink.as_deref().and_then(find_sink);
+
+            // A stream that went away since the listing doesn't stop the others
+            for input in inputs {
+                let name = &setting.name;
+                if let Some(percent) = setting.volume.filter(|_| input.volume_writable) {
+                    let mut channels = input.volume;
+                    channels.set(channels.len(), percent_to_volume(percent));
+                    if let Err(e) = api.set_sink_input_volume(input.index, &channels) {
+                        log::warn!(
+                            "Could not set the volume of '{name}' stream {}: {e}",
+                            input.index
+                        );
+                    }
+                }
+                if let Some(mute) = setting.mute {
+                    if let Err(e) = api.set_sink_input_mute(input.index, mute) {
+                        log::warn!(
+                            "Could not mute or unmute '{name}' stream {}: {e}",
+                            input.index
+                        );
+                    }
+                }
+                if let Some(sink) = sink.filter(|s| s.index != input.sink) {
+                    if let Err(e) = api.move_sink_input(input.index, sink.index) {
+                        log::warn!("Could not move '{name}' stream {}: {e}", input.index);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+//! One line summaries of a sink or stream for status bars
+//!
+//! A [`Template`] is plain text with `{field}` placeholders, where `{{` and `}}` stand for
+//! literal braces. The fields are:
+//!
+//! * `volume`: volume in percent, rounded to a whole number
+//! * `mute`: `muted` when muted, otherwise empty
+//! * `name`: internal name of the sink or stream
+//! * `description`: human readable description, or the application name for streams
+//! * `port`: description of the sink's active port, empty for streams
+//! * `icon`: an icon picked from [`Icons`] by volume and mute state
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use crate::data::{Scale, SinkInformation, SinkInputInformation};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StatusError {
+    #[error("unknown field '{{{0}}}' in template")]
+    UnknownField(String),
+    #[error("unclosed '{{' in template")]
+    UnclosedField,
+    #[error("unmatched '}}' in template, write '}}}}' for a literal brace")]
+    UnmatchedBrace,
+    #[error("invalid icon threshold '{0}', expected <percent>=<icon> or muted=<icon>")]
+    InvalidIcon(String),
+}
+
+/// The values a template can show
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+    pub volume: f64,
+    pub mute: bool,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub port: Option<String>,
+}
+
+impl Status {
+    pub fn from_sink(sink: &SinkInformation, scale: Scale) -> Self {
+        Self {
+            volume: scale.to_percent(sink.volume.avg()),
+            mute: sink.mute,
+            name: sink.name.clone(),
+            description: sink.description.clone(),
+            port: sink
+                .active_port
+                .as_ref()
+                .and_then(|p| p.description.clone().or_else(|| p.name.clone())),
+        }
+    }
+
+    pub fn from_sink_input(input: &SinkInputInformation, scale: Scale) -> Self {
+        Self {
+            volume: scale.to_percent(input.volume.avg()),
+            mute: input.mute,
+            name: input.name.clone(),
+            description: input
+                .proplist
+                .get_str(pulse::proplist::properties::APPLICATION_NAME),
+            port: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Volume,
+    Mute,
+    Name,
+    Description,
+    Port,
+    Icon,
+}
+
+impl FromStr for Field {
+    type Err = StatusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "volume" => Ok(Field::Volume),
+            "mute" => Ok(Field::Mute),
+            "name" => Ok(Field::Name),
+            "description" => Ok(Field::Description),
+            "port" => Ok(Field::Port),
+            "icon" => Ok(Field::Icon),
+            _ => Err(StatusError::UnknownField(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Field(Field),
+}
+
+/// A parsed status line template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn render(&self, status: &Status, icons: &Icons) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Field(Field::Volume) => out.push_str(&format!("{:.0}", status.volume)),
+                Part::Field(Field::Mute) if status.mute => out.push_str("muted"),
+                Part::Field(Field::Mute) => {}
+                Part::Field(Field::Name) => out.push_str(status.name.as_deref().unwrap_or("")),
+                Part::Field(Field::Description) => {
+                    out.push_str(status.description.as_deref().unwrap_or(""))
+                }
+                Part::Field(Field::Port) => out.push_str(status.port.as_deref().unwrap_or("")),
+                Part::Field(Field::Icon) => out.push_str(icons.icon(status.volume, status.mute)),
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for Template {
+    type Err = StatusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err(StatusError::UnmatchedBrace),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(StatusError::UnclosedField),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field(name.trim().parse()?));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+}
+
+/// Icons for the `{icon}` field, picked by the highest threshold the volume reaches.
+///
+/// Written as a comma separated list of `<percent>=<icon>` pairs plus a `muted=<icon>` entry,
+/// for example `muted=🔇,0=🔈,34=🔉,67=🔊`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icons {
+    muted: String,
+    /// Thresholds in percent, in ascending order
+    levels: Vec<(u32, String)>,
+}
+
+impl Icons {
+    pub fn icon(&self, volume: f64, mute: bool) -> &str {
+        if mute {
+            return &self.muted;
+        }
+        self.levels
+            .iter()
+            .rev()
+            .find(|(threshold, _)| volume.round() >= *threshold as f64)
+            .or(self.levels.first())
+            .map(|(_, icon)| icon.as_str())
+            .unwrap_or("")
+    }
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        "muted=🔇,0=🔈,34=🔉,67=🔊"
+            .parse()
+            .expect("default icons are valid")
+    }
+}
+
+impl FromStr for Icons {
+    type Err = StatusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut muted = String::new();
+        let mut levels = vec![];
+        for entry in s.split(',').filter(|e| !e.is_empty()) {
+            let invalid = || StatusError::InvalidIcon(entry.to_string());
+            let (key, icon) = entry.split_once('=').ok_or_else(invalid)?;
+            match key.trim() {
+                "muted" => muted = icon.to_string(),
+                threshold => {
+                    levels.push((threshold.parse().map_err(|_| invalid())?, icon.to_string()))
+                }
+            }
+        }
+        levels.sort_by_key(|(threshold, _)| *threshold);
+        Ok(Icons { muted, levels })
+    }
+}
+
+impl fmt::Display for Icons {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "muted={}", self.muted)?;
+        for (threshold, icon) in &self.levels {
+            write!(f, ",{threshold}={icon}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(volume: f64, mute: bool) -> Status {
+        Status {
+            volume,
+            mute,
+            name: Some("alsa_output.usb".into()),
+            description: Some("USB Headset".into()),
+            port: None,
+        }
+    }
+
+    fn render(template: &str, status: &Status) -> String {
+        template
+            .parse::<Template>()
+            .unwrap()
+            .render(status, &Icons::default())
+    }
+
+    #[test]
+    fn renders_fields_and_literals() {
+        assert_eq!(
+            render("{description}: {volume}% {mute}", &status(42.4, true)),
+            "USB Headset: 42% muted"
+        );
+        assert_eq!(
+            render("{ name }{port}", &status(0.0, false)),
+            "alsa_output.usb"
+        );
+        assert_eq!(render("{{{volume}}}", &status(99.6, false)), "{100}");
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        let parse = |s: &str| s.parse::<Template>().map(|_| ());
+        assert_eq!(
+            parse("{level}"),
+            Err(StatusError::UnknownField("level".into()))
+        );
+        assert_eq!(parse("{volume"), Err(StatusError::UnclosedField));
+        assert_eq!(parse("volume}"), Err(StatusError::UnmatchedBrace));
+    }
+
+    #[test]
+    fn icons_pick_the_highest_threshold_reached() {
+        let icons: Icons = "muted=M,0=low,34=mid,67=high".parse().unwrap();
+        assert_eq!(icons.icon(0.0, false), "low");
+        assert_eq!(icons.icon(33.6, false), "mid");
+        assert_eq!(icons.icon(66.4, false), "mid");
+        assert_eq!(icons.icon(150.0, false), "high");
+        assert_eq!(icons.icon(80.0, true), "M");
+    }
+
+    #[test]
+    fn icons_below_every_threshold_use_the_lowest() {
+        let icons: Icons = "50=half,10=low".parse().unwrap();
+        assert_eq!(icons.icon(5.0, false), "low");
+        assert_eq!(icons.to_string(), "muted=,10=low,50=half");
+    }
+
+    #[test]
+    fn rejects_malformed_icons() {
+        for text in ["loud", "x=icon"] {
+            assert_eq!(
+                text.parse::<Icons>(),
+                Err(StatusError::InvalidIcon(text.into()))
+            );
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! Addressing sinks and streams by name or index
+//!
+//! A target is written as `[kind:]selector`, where `kind` is `sink` (the default) or `input`,
+//! and `selector` is an index, a name, or `default`/`@DEFAULT_SINK@` for the default sink.
+//!
+//! What `tmix kill` disconnects is written the same way, with `input` (the default), `output`
+//! for recording streams, or `client`.
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::data::{
+    ClientInformation, SinkInformation, SinkInputInformation, SourceOutputInformation,
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TargetError {
+    #[error("empty target")]
+    Empty,
+    #[error("'{0}' does not refer to a stream")]
+    NotAStream(String),
+}
+
+/// The kind of object a target refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetKind {
+    Sink,
+    SinkInput,
+}
+
+/// How a target picks out an object of its kind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    /// The server's default sink
+    Default,
+    Index(u32),
+    Name(String),
+}
+
+impl Selector {
+    /// Whether a sink is selected, given the name of the server's default sink
+    pub fn matches_sink(&self, sink: &SinkInformation, default_sink: Option<&str>) -> bool {
+        match self {
+            Selector::Default => default_sink.is_some() && sink.name.as_deref() == default_sink,
+            Selector::Index(i) => sink.index == *i,
+            Selector::Name(name) => {
+                sink.name.as_deref() == Some(name.as_str())
+                    || sink
+                        .description
+                        .as_ref()
+                        .is_some_and(|d| d.eq_ignore_ascii_case(name))
+            }
+        }
+    }
+
+    /// Whether a sink input is selected. Names match either the stream name or the name of the
+    /// application playing it, so one name can select several streams.
+    pub fn matches_sink_input(&self, input: &SinkInputInformation) -> bool {
+        match self {
+            Selector::Default => false,
+            Selector::Index(i) => input.index == *i,
+            Selector::Name(name) => {
+                input
+                    .name
+                    .as_ref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(name))
+                    || input
+                        .proplist
+                        .get_str(pulse::proplist::properties::APPLICATION_NAME)
+                        .is_some_and(|n| n.eq_ignore_ascii_case(name))
+            }
+        }
+    }
+
+    /// Whether a source output is selected, by its name or the name of the application
+    /// recording
+    pub fn matches_source_output(&self, output: &SourceOutputInformation) -> bool {
+        match self {
+            Selector::Default => false,
+            Selector::Index(i) => output.index == *i,
+            Selector::Name(name) => {
+                output
+                    .name
+                    .as_ref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(name))
+                    || output
+                        .proplist
+                        .get_str(pulse::proplist::properties::APPLICATION_NAME)
+                        .is_some_and(|n| n.eq_ignore_ascii_case(name))
+            }
+        }
+    }
+
+    /// Whether a client is selected, by its own name or its application name
+    pub fn matches_client(&self, client: &ClientInformation) -> bool {
+        match self {
+            Selector::Default => false,
+            Selector::Index(i) => client.index == *i,
+            Selector::Name(name) => {
+                client
+                    .name
+                    .as_ref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(name))
+                    || client.display_name().eq_ignore_ascii_case(name)
+            }
+        }
+    }
+}
+
+impl FromStr for Selector {
+    type Err = TargetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err(TargetError::Empty),
+            "default" | "@DEFAULT_SINK@" => Ok(Selector::Default),
+            _ => Ok(s
+                .parse()
+                .map(Selector::Index)
+                .unwrap_or_else(|_| Selector::Name(s.to_string()))),
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Default => write!(f, "default"),
+            Selector::Index(i) => write!(f, "{i}"),
+            Selector::Name(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+impl Serialize for Selector {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Selector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A sink or sink input named on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub kind: TargetKind,
+    pub selector: Selector,
+}
+
+impl Target {
+    /// Parse a target that must refer to sink inputs, where the `input:` prefix is optional
+    pub fn parse_sink_input(s: &str) -> Result<Self, TargetError> {
+        if s.starts_with("sink:") {
+            return Err(TargetError::NotAStream(s.to_string()));
+        }
+        Ok(Target {
+            kind: TargetKind::SinkInput,
+            selector: s.strip_prefix("input:").unwrap_or(s).parse()?,
+        })
+    }
+}
+
+impl FromStr for Target {
+    type Err = TargetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Device names can contain ':' themselves, so only known prefixes are treated as kinds
+        let (kind, selector) = match s.split_once(':') {
+            Some(("sink", selector)) => (TargetKind::Sink, selector),
+            Some(("input", selector)) => (TargetKind::SinkInput, selector),
+            _ => (TargetKind::Sink, s),
+        };
+        Ok(Target {
+            kind,
+            selector: selector.parse()?,
+        })
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TargetKind::Sink => write!(f, "sink:{}", self.selector),
+            TargetKind::SinkInput => write!(f, "input:{}", self.selector),
+        }
+    }
+}
+
+impl Serialize for Target {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Target {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A stream or client to disconnect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KillTarget {
+    SinkInput(Selector),
+    SourceOutput(Selector),
+    Client(Selector),
+}
+
+impl FromStr for KillTarget {
+    type Err = TargetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("input", selector)) => Ok(KillTarget::SinkInput(selector.parse()?)),
+            Some(("output", selector)) => Ok(KillTarget::SourceOutput(selector.parse()?)),
+            Some(("client", selector)) => Ok(KillTarget::Client(selector.parse()?)),
+            Some(("sink", _)) => Err(TargetError::NotAStream(s.to_string())),
+            _ => Ok(KillTarget::SinkInput(s.parse()?)),
+        }
+    }
+}
+
+impl fmt::Display for KillTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KillTarget::SinkInput(selector) => write!(f, "input:{selector}"),
+            KillTarget::SourceOutput(selector) => write!(f, "output:{selector}"),
+            KillTarget::Client(selector) => write!(f, "client:{selector}"),
+        }
+    }
+}
+
+impl Serialize for KillTarget {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KillTarget {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(kind: TargetKind, selector: Selector) -> Target {
+        Target { kind, selector }
+    }
+
+    #[test]
+    fn selectors() {
+        assert_eq!("default".parse(), Ok(Selector::Default));
+        assert_eq!("@DEFAULT_SINK@".parse(), Ok(Selector::Default));
+        assert_eq!("12".parse(), Ok(Selector::Index(12)));
+        assert_eq!("Firefox".parse(), Ok(Selector::Name("Firefox".into())));
+        assert_eq!("".parse::<Selector>(), Err(TargetError::Empty));
+    }
+
+    #[test]
+    fn targets_default_to_sinks() {
+        assert_eq!(
+            "headset".parse(),
+            Ok(target(TargetKind::Sink, Selector::Name("headset".into())))
+        );
+        assert_eq!(
+            "sink:3".parse(),
+            Ok(target(TargetKind::Sink, Selector::Index(3)))
+        );
+        assert_eq!(
+            "input:default".parse(),
+            Ok(target(TargetKind::SinkInput, Selector::Default))
+        );
+        assert_eq!("input:".parse::<Target>(), Err(TargetError::Empty));
+    }
+
+    #[test]
+    fn names_keep_unknown_prefixes() {
+        let name = "alsa_output.pci-0000:00:1f.3.analog-stereo";
+        assert_eq!(
+            name.parse(),
+            Ok(target(TargetKind::Sink, Selector::Name(name.into())))
+        );
+    }
+
+    #[test]
+    fn sink_input_targets() {
+        assert_eq!(
+            Target::parse_sink_input("7"),
+            Ok(target(TargetKind::SinkInput, Selector::Index(7)))
+        );
+        assert_eq!(
+            Target::parse_sink_input("input:mpv"),
+            Ok(target(TargetKind::SinkInput, Selector::Name("mpv".into())))
+        );
+        assert_eq!(
+            Target::parse_sink_input("sink:1"),
+            Err(TargetError::NotAStream("sink:1".into()))
+        );
+    }
+
+    #[test]
+    fn targets_round_trip_through_display() {
+        for text in ["sink:default", "sink:4", "input:Firefox"] {
+            assert_eq!(text.parse::<Target>().unwrap().to_string(), text);
+        }
+    }
+}
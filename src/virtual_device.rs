@@ -0,0 +1,328 @@
+//! Virtual devices built from PulseAudio modules: null sinks, combined sinks, loopbacks and
+//! remapped sinks
+//!
+//! Every device tmix creates carries a `tmix.virtual=<kind>` property in its module arguments,
+//! so the devices can be found and torn down again later, even from another tmix process or
+//! after tmix restarted.
+use std::{fmt, io, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{data::ModuleInformation, pulse_api::PulseAPI, target::Selector};
+
+/// Property marking the devices tmix created, with the kind of device as its value
+pub const MARKER: &str = "tmix.virtual";
+
+/// Latency of a loopback unless another one is asked for. PulseAudio's own default of 200ms is
+/// too much to monitor a microphone.
+pub const DEFAULT_LOOPBACK_LATENCY_MSEC: u32 = 50;
+
+#[derive(Debug, Error)]
+pub enum VirtualError {
+    #[error("invalid {field} '{value}': {reason}")]
+    Invalid {
+        field: &'static str,
+        value: String,
+        reason: &'static str,
+    },
+    #[error("no virtual device matches {0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Pulse(#[from] io::Error),
+}
+
+/// The kinds of virtual device, named as on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    NullSink,
+    CombineSink,
+    Loopback,
+    RemapSink,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 4] = [
+        Kind::NullSink,
+        Kind::CombineSink,
+        Kind::Loopback,
+        Kind::RemapSink,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::NullSink => "null-sink",
+            Kind::CombineSink => "combine-sink",
+            Kind::Loopback => "loopback",
+            Kind::RemapSink => "remap-sink",
+        }
+    }
+
+    /// The PulseAudio module that creates this kind of device
+    pub fn module(self) -> &'static str {
+        match self {
+            Kind::NullSink => "module-null-sink",
+            Kind::CombineSink => "module-combine-sink",
+            Kind::Loopback => "module-loopback",
+            Kind::RemapSink => "module-remap-sink",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Kind::NullSink => "A sink that plays nowhere, whose monitor can be recorded",
+            Kind::CombineSink => "A sink that plays on several sinks at once",
+            Kind::Loopback => "Play what a source records on a sink",
+            Kind::RemapSink => "A sink with the channels of another sink remapped",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Kind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| format!("'{s}' is not a kind of virtual device"))
+    }
+}
+
+/// A virtual device to create
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VirtualDevice {
+    NullSink {
+        name: String,
+        description: Option<String>,
+    },
+    /// Plays on the named sinks, or on every sink when none are named
+    CombineSink {
+        name: String,
+        description: Option<String>,
+        sinks: Vec<String>,
+    },
+    /// Plays the source on the sink, the defaults when left out
+    Loopback {
+        source: Option<String>,
+        sink: Option<String>,
+        latency_msec: u32,
+    },
+    /// Plays on the master sink with the given channel map, e.g. `mono` to downmix
+    RemapSink {
+        name: String,
+        description: Option<String>,
+        master: String,
+        channel_map: Option<String>,
+    },
+}
+
+impl VirtualDevice {
+    pub fn kind(&self) -> Kind {
+        match self {
+            VirtualDevice::NullSink { .. } => Kind::NullSink,
+            VirtualDevice::CombineSink { .. } => Kind::CombineSink,
+            VirtualDevice::Loopback { .. } => Kind::Loopback,
+            VirtualDevice::RemapSink { .. } => Kind::RemapSink,
+        }
+    }
+
+    /// The argument string to load the module with, checking that every value can be passed
+    /// to PulseAudio as it is
+    pub fn argument(&self) -> Result<String, VirtualError> {
+        let marker = format!("{MARKER}={}", self.kind());
+        let arguments = match self {
+            VirtualDevice::NullSink { name, description } => {
+                vec![
+                    format!("sink_name={}", checked_name("sink name", name)?),
+                    sink_properties(name, description, &marker)?,
+                ]
+            }
+            VirtualDevice::CombineSink {
+                name,
+                description,
+                sinks,
+            } => {
+                let mut arguments = vec![
+                    format!("sink_name={}", checked_name("sink name", name)?),
+                    sink_properties(name, description, &marker)?,
+                ];
+                if !sinks.is_empty() {
+                    for sink in sinks {
+                        checked_name("sink", sink)?;
+                    }
+                    arguments.push(format!("slaves={}", sinks.join(",")));
+                }
+                arguments
+            }
+            VirtualDevice::Loopback {
+                source,
+                sink,
+                latency_msec,
+            } => {
+                let mut arguments = vec![format!("latency_msec={latency_msec}")];
+                // Named devices are kept, rather than following the default as it changes
+                if let Some(source) = source {
+                    arguments.push(format!("source={}", checked_name("source", source)?));
+                    arguments.push("source_dont_move=true".to_string());
+                }
+                if let Some(sink) = sink {
+                    arguments.push(format!("sink={}", checked_name("sink", sink)?));
+                    arguments.push("sink_dont_move=true".to_string());
+                }
+                arguments.push(format!("sink_input_properties=\"{marker}\""));
+                arguments.push(format!("source_output_properties=\"{marker}\""));
+                arguments
+            }
+            VirtualDevice::RemapSink {
+                name,
+                description,
+                master,
+                channel_map,
+            } => {
+                let mut arguments = vec![
+                    format!("sink_name={}", checked_name("sink name", name)?),
+                    format!("master={}", checked_name("master sink", master)?),
+                    sink_properties(name, description, &marker)?,
+                ];
+                if let Some(map) = channel_map {
+                    let map = checked_name("channel map", map)?;
+                    let channels = map.split(',').count();
+                    arguments.push(format!("channels={channels} channel_map={map}"));
+                }
+                arguments
+            }
+        };
+        Ok(arguments.join(" "))
+    }
+}
+
+/// Names are passed unquoted, so they can't be empty or hold spaces or quotes
+fn checked_name<'a>(field: &'static str, name: &'a str) -> Result<&'a str, VirtualError> {
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        return Err(VirtualError::Invalid {
+            field,
+            value: name.to_string(),
+            reason: "it can't be empty or contain spaces or quotes",
+        });
+    }
+    Ok(name)
+}
+
+/// The properties of a new sink: its description, which defaults to its name, and the marker
+fn sink_properties(
+    name: &str,
+    description: &Option<String>,
+    marker: &str,
+) -> Result<String, VirtualError> {
+    let description = description.as_deref().unwrap_or(name);
+    if description.contains(['"', '\'']) {
+        return Err(VirtualError::Invalid {
+            field: "description",
+            value: description.to_string(),
+            reason: "it can't contain quotes",
+        });
+    }
+    Ok(format!(
+        "sink_properties=\"device.description='{description}' {marker}\""
+    ))
+}
+
+/// A loaded module that tmix created a virtual device with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tracked {
+    /// Index of the module, which unloading removes the device
+    pub module: u32,
+    pub kind: Kind,
+    /// Name of the sink the module created, loopbacks have none
+    pub sink_name: Option<String>,
+    pub argument: String,
+}
+
+impl Tracked {
+    /// The tracked device a module created, if tmix created it
+    pub fn from_module(module: &ModuleInformation) -> Option<Self> {
+        let argument = module.argument.as_ref()?;
+        let kind = argument_value(argument, MARKER)?.parse().ok()?;
+        Some(Self {
+            module: module.index,
+            kind,
+            sink_name: argument_value(argument, "sink_name").map(str::to_string),
+            argument: argument.clone(),
+        })
+    }
+
+    /// Whether the selector names the module's index or the sink it created
+    pub fn matches(&self, selector: &Selector) -> bool {
+        match selector {
+            Selector::Default => false,
+            Selector::Index(i) => self.module == *i,
+            Selector::Name(name) => self.sink_name.as_deref() == Some(name.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for Tracked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.module, self.kind)?;
+        if let Some(name) = &self.sink_name {
+            write!(f, " {name}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The value of `key=` in an argument string, up to the next space or quote
+fn argument_value<'a>(argument: &'a str, key: &str) -> Option<&'a str> {
+    let pattern = format!("{key}=");
+    let start = argument
+        .match_indices(&pattern)
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || argument[..i].ends_with([' ', '"', '\'']))?
+        + pattern.len();
+    let rest = &argument[start..];
+    let end = rest
+        .find(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+        .unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+/// Create a virtual device, returning the index of the module it was loaded with
+pub fn create(api: &mut PulseAPI, device: &VirtualDevice) -> Result<u32, VirtualError> {
+    let argument = device.argument()?;
+    log::debug!("Loading {} {argument}", device.kind().module());
+    Ok(api.load_module(device.kind().module(), &argument)?)
+}
+
+/// The virtual devices tmix created that are still loaded
+pub fn list(api: &mut PulseAPI) -> Result<Vec<Tracked>, VirtualError> {
+    Ok(api
+        .get_modules()?
+        .iter()
+        .filter_map(Tracked::from_module)
+        .collect())
+}
+
+/// Tear down the virtual devices the selector names, or all of them without one, and return
+/// what was removed
+pub fn remove(
+    api: &mut PulseAPI,
+    selector: Option<&Selector>,
+) -> Result<Vec<Tracked>, VirtualError> {
+    let removed: Vec<Tracked> = list(api)?
+        .into_iter()
+        .filter(|device| selector.is_none_or(|s| device.matches(s)))
+        .collect();
+    if let (Some(selector), true) = (selector, removed.is_empty()) {
+        return Err(VirtualError::NotFound(selector.to_string()));
+    }
+    for device in &removed {
+        api.unload_module(device.module)?;
+    }
+    Ok(removed)
+}
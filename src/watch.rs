@@ -0,0 +1,419 @@
+//! Describing what changed between two snapshots of the server
+//!
+//! The server only tells us *which* object changed, so the events here are worked out by
+//! comparing the state before and after a change notification.
+use serde::Serialize;
+
+use crate::{
+    data::{Scale, SinkInformation, SinkInputInformation},
+    pulse_api::VolumeInfo,
+    target::{Selector, Target, TargetKind},
+};
+
+/// A single change, serialised as one JSON object tagged by `event`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ChangeEvent {
+    SinkAdded {
+        index: u32,
+        name: Option<String>,
+    },
+    SinkRemoved {
+        index: u32,
+        name: Option<String>,
+    },
+    SinkVolumeChanged {
+        index: u32,
+        name: Option<String>,
+        /// Volume in percent
+        volume: f64,
+    },
+    SinkMuteChanged {
+        index: u32,
+        name: Option<String>,
+        mute: bool,
+    },
+    SinkInputAdded {
+        index: u32,
+        name: Option<String>,
+        application: Option<String>,
+        sink: u32,
+    },
+    SinkInputRemoved {
+        index: u32,
+        name: Option<String>,
+    },
+    SinkInputVolumeChanged {
+        index: u32,
+        name: Option<String>,
+        /// Volume in percent
+        volume: f64,
+    },
+    SinkInputMuteChanged {
+        index: u32,
+        name: Option<String>,
+        mute: bool,
+    },
+    SinkInputMoved {
+        index: u32,
+        name: Option<String>,
+        sink: u32,
+    },
+    DefaultSinkChanged {
+        name: Option<String>,
+    },
+}
+
+impl ChangeEvent {
+    /// Work out the events that turn `old` into `new`, with volumes in percent on `scale`
+    pub fn diff(old: &VolumeInfo, new: &VolumeInfo, scale: Scale) -> Vec<ChangeEvent> {
+        let mut events = vec![];
+
+        if old.default_sink_name() != new.default_sink_name() {
+            events.push(ChangeEvent::DefaultSinkChanged {
+                name: new.default_sink_name().map(str::to_string),
+            });
+        }
+
+        for (index, entry) in new.iter() {
+            let sink = entry.sink();
+            match old.sink(*index) {
+                None => events.push(ChangeEvent::SinkAdded {
+                    index: *index,
+                    name: sink.name.clone(),
+                }),
+                Some(before) => events.extend(Self::sink_changes(before, sink, scale)),
+            }
+        }
+        for (index, entry) in old.iter() {
+            if new.sink(*index).is_none() {
+                events.push(ChangeEvent::SinkRemoved {
+                    index: *index,
+                    name: entry.sink().name.clone(),
+                });
+            }
+        }
+
+        for input in new.sink_inputs() {
+            match old.sink_input(input.index) {
+                None => events.push(ChangeEvent::SinkInputAdded {
+                    index: input.index,
+                    name: input.name.clone(),
+                    application: input
+                        .proplist
+                        .get_str(pulse::proplist::properties::APPLICATION_NAME),
+                    sink: input.sink,
+                }),
+                Some(before) => events.extend(Self::sink_input_changes(before, input, scale)),
+            }
+        }
+        for input in old.sink_inputs() {
+            if new.sink_input(input.index).is_none() {
+                events.push(ChangeEvent::SinkInputRemoved {
+                    index: input.index,
+                    name: input.name.clone(),
+                });
+            }
+        }
+
+        events
+    }
+
+    fn sink_changes(
+        before: &SinkInformation,
+        after: &SinkInformation,
+        scale: Scale,
+    ) -> Vec<ChangeEvent> {
+        let mut events = vec![];
+        if before.volume != after.volume {
+            events.push(ChangeEvent::SinkVolumeChanged {
+                index: after.index,
+                name: after.name.clone(),
+                volume: scale.to_percent(after.volume.avg()),
+            });
+        }
+        if before.mute != after.mute {
+            events.push(ChangeEvent::SinkMuteChanged {
+                index: after.index,
+                name: after.name.clone(),
+                mute: after.mute,
+            });
+        }
+        events
+    }
+
+    fn sink_input_changes(
+        before: &SinkInputInformation,
+        after: &SinkInputInformation,
+        scale: Scale,
+    ) -> Vec<ChangeEvent> {
+        let mut events = vec![];
+        if before.volume != after.volume {
+            events.push(ChangeEvent::SinkInputVolumeChanged {
+                index: after.index,
+                name: after.name.clone(),
+                volume: scale.to_percent(after.volume.avg()),
+            });
+        }
+        if before.mute != after.mute {
+            events.push(ChangeEvent::SinkInputMuteChanged {
+                index: after.index,
+                name: after.name.clone(),
+                mute: after.mute,
+            });
+        }
+        if before.sink != after.sink {
+            events.push(ChangeEvent::SinkInputMoved {
+                index: after.index,
+                name: after.name.clone(),
+                sink: after.sink,
+            });
+        }
+        events
+    }
+
+    /// Whether the event is about the target, looking it up in the snapshots either side of the
+    /// change so that added and removed objects are matched too
+    pub fn concerns(&self, target: &Target, old: &VolumeInfo, new: &VolumeInfo) -> bool {
+        let selector = &target.selector;
+        let sink_matches = |index: &u32| {
+            [old, new].iter().any(|info| {
+                info.sink(*index)
+                    .is_some_and(|s| selector.matches_sink(s, info.default_sink_name()))
+            })
+        };
+        let input_matches = |index: &u32| {
+            [old, new].iter().any(|info| {
+                info.sink_input(*index)
+                    .is_some_and(|i| selector.matches_sink_input(i))
+            })
+        };
+
+        match (target.kind, self) {
+            (TargetKind::Sink, ChangeEvent::DefaultSinkChanged { .. }) => {
+                *selector == Selector::Default
+            }
+            (
+                TargetKind::Sink,
+                ChangeEvent::SinkAdded { index, .. }
+                | ChangeEvent::SinkRemoved { index, .. }
+                | ChangeEvent::SinkVolumeChanged { index, .. }
+                | ChangeEvent::SinkMuteChanged { index, .. },
+            ) => sink_matches(index),
+            (
+                TargetKind::SinkInput,
+                ChangeEvent::SinkInputAdded { index, .. }
+                | ChangeEvent::SinkInputRemoved { index, .. }
+                | ChangeEvent::SinkInputVolumeChanged { index, .. }
+                | ChangeEvent::SinkInputMuteChanged { index, .. }
+                | ChangeEvent::SinkInputMoved { index, .. },
+            ) => input_matches(index),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pulse::{
+        channelmap,
+        def::{SinkFlagSet, SinkState},
+        format,
+        proplist::{properties::APPLICATION_NAME, Proplist},
+        sample,
+        time::MicroSeconds,
+        volume::{ChannelVolumes, Volume},
+    };
+
+    use super::*;
+
+    /// Stereo channels at the same volume
+    fn stereo(volume: Volume) -> ChannelVolumes {
+        let mut channels = ChannelVolumes::default();
+        channels.set_len(2);
+        channels.get_mut().fill(volume);
+        channels
+    }
+
+    fn spec() -> sample::Spec {
+        sample::Spec {
+            format: sample::Format::S16le,
+            rate: 44100,
+            channels: 2,
+        }
+    }
+
+    fn sink(index: u32, name: &str) -> SinkInformation {
+        SinkInformation {
+            name: Some(name.to_string()),
+            index,
+            description: None,
+            sample_spec: spec(),
+            channel_map: channelmap::Map::default(),
+            owner_module: None,
+            volume: stereo(Volume::NORMAL),
+            mute: false,
+            monitor_source: 0,
+            monitor_source_name: None,
+            latency: MicroSeconds(0),
+            driver: None,
+            flags: SinkFlagSet::empty(),
+            proplist: Proplist::new().expect("proplist"),
+            configured_latency: MicroSeconds(0),
+            base_volume: Volume::NORMAL,
+            state: SinkState::Running,
+            n_volume_steps: 0,
+            card: None,
+            formats: vec![],
+            ports: vec![],
+            active_port: None,
+        }
+    }
+
+    /// A stream of the named application playing on `sink`
+    fn application_input(index: u32, sink: u32, application: &str) -> SinkInputInformation {
+        let mut proplist = Proplist::new().expect("proplist");
+        proplist
+            .set_str(APPLICATION_NAME, application)
+            .expect("property");
+        SinkInputInformation {
+            index,
+            name: Some("playback".to_string()),
+            owner_module: None,
+            client: None,
+            sink,
+            sample_spec: spec(),
+            channel_map: channelmap::Map::default(),
+            volume: stereo(Volume::NORMAL),
+            buffer_usec: MicroSeconds(0),
+            sink_usec: MicroSeconds(0),
+            resample_method: None,
+            driver: None,
+            mute: false,
+            proplist,
+            corked: false,
+            has_volume: true,
+            volume_writable: true,
+            format: format::Info::new().expect("format"),
+        }
+    }
+
+    fn info(
+        sinks: Vec<SinkInformation>,
+        inputs: Vec<SinkInputInformation>,
+        default_sink: &str,
+    ) -> VolumeInfo {
+        VolumeInfo::new(sinks, inputs, Some(default_sink.to_string()))
+    }
+
+    #[test]
+    fn nothing_changed() {
+        let old = info(vec![sink(0, "speakers")], vec![], "speakers");
+        let new = info(vec![sink(0, "speakers")], vec![], "speakers");
+        assert_eq!(ChangeEvent::diff(&old, &new, Scale::Linear), vec![]);
+    }
+
+    #[test]
+    fn sinks_added_and_removed() {
+        let old = info(vec![sink(0, "speakers")], vec![], "speakers");
+        let new = info(vec![sink(1, "headset")], vec![], "headset");
+        assert_eq!(
+            ChangeEvent::diff(&old, &new, Scale::Linear),
+            vec![
+                ChangeEvent::DefaultSinkChanged {
+                    name: Some("headset".into())
+                },
+                ChangeEvent::SinkAdded {
+                    index: 1,
+                    name: Some("headset".into())
+                },
+                ChangeEvent::SinkRemoved {
+                    index: 0,
+                    name: Some("speakers".into())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn volumes_are_reported_on_the_scale() {
+        let old = info(vec![sink(0, "speakers")], vec![], "speakers");
+        let mut quieter = sink(0, "speakers");
+        quieter.volume = stereo(Volume(Volume::NORMAL.0 / 2));
+        quieter.mute = true;
+        let new = info(vec![quieter], vec![], "speakers");
+        assert_eq!(
+            ChangeEvent::diff(&old, &new, Scale::Cubic),
+            vec![
+                ChangeEvent::SinkVolumeChanged {
+                    index: 0,
+                    name: Some("speakers".into()),
+                    volume: 50.0
+                },
+                ChangeEvent::SinkMuteChanged {
+                    index: 0,
+                    name: Some("speakers".into()),
+                    mute: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn streams_added_moved_and_removed() {
+        let sinks = || vec![sink(0, "speakers"), sink(1, "headset")];
+        let old = info(
+            sinks(),
+            vec![
+                application_input(5, 0, "mpv"),
+                application_input(6, 0, "Zoom"),
+            ],
+            "speakers",
+        );
+        let new = info(
+            sinks(),
+            vec![
+                application_input(6, 1, "Zoom"),
+                application_input(7, 0, "Firefox"),
+            ],
+            "speakers",
+        );
+        let events = ChangeEvent::diff(&old, &new, Scale::Linear);
+        assert_eq!(
+            events,
+            vec![
+                ChangeEvent::SinkInputAdded {
+                    index: 7,
+                    name: Some("playback".into()),
+                    application: Some("Firefox".into()),
+                    sink: 0
+                },
+                ChangeEvent::SinkInputMoved {
+                    index: 6,
+                    name: Some("playback".into()),
+                    sink: 1
+                },
+                ChangeEvent::SinkInputRemoved {
+                    index: 5,
+                    name: Some("playback".into())
+                },
+            ]
+        );
+
+        let zoom: Target = "input:zoom".parse().unwrap();
+        let concerning: Vec<_> = events
+            .iter()
+            .filter(|event| event.concerns(&zoom, &old, &new))
+            .collect();
+        assert_eq!(concerning, vec![&events[1]]);
+    }
+
+    #[test]
+    fn default_sink_changes_concern_the_default_target() {
+        let old = info(vec![sink(0, "speakers")], vec![], "speakers");
+        let new = info(vec![sink(0, "speakers")], vec![], "none");
+        let event = &ChangeEvent::diff(&old, &new, Scale::Linear)[0];
+        assert!(event.concerns(&"sink:default".parse().unwrap(), &old, &new));
+        assert!(!event.concerns(&"sink:speakers".parse().unwrap(), &old, &new));
+    }
+}